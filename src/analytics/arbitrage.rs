@@ -0,0 +1,188 @@
+use super::book::{self, order_price};
+use super::range::sqrt_price_at;
+use crate::commands::lp::PoolOrders;
+use crate::util::util::{asset_in_amount, get_decimals, tick_to_price};
+use rust_decimal::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Buy base in the pool, it's cheaper than the reference.
+    Buy,
+    /// Sell base into the pool, it pays more than the reference.
+    Sell,
+}
+
+/// Trade available before the pool price converges to the reference price.
+#[derive(Clone, Copy, Debug)]
+pub struct Arbitrage {
+    pub direction: Direction,
+    pub base_amount: f64,
+    pub quote_amount: f64,
+    /// Against trading the same base amount at the reference price, in the quote asset.
+    pub profit: f64,
+}
+
+/// Sizes the arbitrage between the pool and `reference`, if there is one. Limit orders
+/// priced past the reference are taken whole, and the active range liquidity is assumed to
+/// stay constant while the pool price moves to the reference.
+pub fn opportunity(
+    orders: &PoolOrders,
+    base: &str,
+    quote: &str,
+    current_tick: i32,
+    reference: f64,
+) -> Option<Arbitrage> {
+    let best_ask = book::best_ask(orders).map(|order| order_price(order, base, quote));
+    let best_bid = book::best_bid(orders).map(|order| order_price(order, base, quote));
+    let pool_price = tick_to_price(current_tick, base, quote) as f64;
+    let direction = if best_ask.is_some_and(|ask| ask < reference) || pool_price < reference {
+        Direction::Buy
+    } else if best_bid.is_some_and(|bid| bid > reference) || pool_price > reference {
+        Direction::Sell
+    } else {
+        return None;
+    };
+    let mut arbitrage = Arbitrage {
+        direction,
+        base_amount: 0.0,
+        quote_amount: 0.0,
+        profit: 0.0,
+    };
+    let amount = |value, asset| asset_in_amount(value, asset).to_f64().unwrap_or_default();
+    match direction {
+        Direction::Buy => {
+            for order in &orders.limit_orders.asks {
+                let price = order_price(order, base, quote);
+                if price < reference {
+                    let base_amount = amount(&order.sell_amount, base);
+                    arbitrage.base_amount += base_amount;
+                    arbitrage.quote_amount += base_amount * price;
+                    arbitrage.profit += base_amount * (reference - price);
+                }
+            }
+        }
+        Direction::Sell => {
+            for order in &orders.limit_orders.bids {
+                let price = order_price(order, base, quote);
+                if price > reference {
+                    let quote_amount = amount(&order.sell_amount, quote);
+                    arbitrage.base_amount += quote_amount / price;
+                    arbitrage.quote_amount += quote_amount;
+                    arbitrage.profit += quote_amount / price * (price - reference);
+                }
+            }
+        }
+    }
+    let moves_towards_reference = match direction {
+        Direction::Buy => pool_price < reference,
+        Direction::Sell => pool_price > reference,
+    };
+    if moves_towards_reference {
+        let liquidity = book::summarize(orders, base, quote, current_tick).range_liquidity;
+        let base_scale = 10_f64.powi(get_decimals(base));
+        let quote_scale = 10_f64.powi(get_decimals(quote));
+        let current = sqrt_price_at(current_tick);
+        let target = (reference * quote_scale / base_scale).sqrt();
+        let base_amount = (liquidity * (1.0 / current - 1.0 / target)).abs() / base_scale;
+        let quote_amount = (liquidity * (target - current)).abs() / quote_scale;
+        arbitrage.base_amount += base_amount;
+        arbitrage.quote_amount += quote_amount;
+        arbitrage.profit += (base_amount * reference - quote_amount).abs();
+    }
+    Some(arbitrage)
+}
+
+/// Deviation of `price` from `reference` in basis points.
+pub fn deviation_bps(price: f64, reference: f64) -> f64 {
+    (price - reference) / reference * 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::lp::{AskBidMap, LimitOrder};
+    use crate::util::util::price_to_tick;
+    use web3::types::U256;
+
+    const BASE: &str = "ETH";
+    const QUOTE: &str = "USDC";
+
+    fn order(price: f64, sell_amount: U256) -> LimitOrder {
+        LimitOrder {
+            lp: "cFLp".to_string(),
+            id: U256::zero(),
+            tick: price_to_tick(price, BASE, QUOTE),
+            sell_amount,
+            fees_earned: U256::zero(),
+            original_sell_amount: sell_amount,
+        }
+    }
+
+    fn book(asks: Vec<LimitOrder>, bids: Vec<LimitOrder>) -> PoolOrders {
+        PoolOrders {
+            limit_orders: AskBidMap { asks, bids },
+            range_orders: vec![],
+        }
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected.abs() * 0.01,
+            "{actual} is not within 1% of {expected}"
+        );
+    }
+
+    #[test]
+    fn no_opportunity_when_the_book_straddles_the_reference() {
+        let orders = book(
+            vec![order(2100.0, U256::exp10(18))],
+            vec![order(1900.0, U256::from(1_900_000_000u64))],
+        );
+        let tick = price_to_tick(2000.0, BASE, QUOTE);
+        let reference = tick_to_price(tick, BASE, QUOTE) as f64;
+        assert!(opportunity(&orders, BASE, QUOTE, tick, reference).is_none());
+    }
+
+    #[test]
+    fn buys_asks_below_the_reference() {
+        let orders = book(
+            vec![
+                order(2000.0, U256::exp10(18)),
+                order(2200.0, U256::exp10(18)),
+            ],
+            vec![],
+        );
+        let tick = price_to_tick(2100.0, BASE, QUOTE);
+        let reference = tick_to_price(tick, BASE, QUOTE) as f64;
+        let arbitrage = opportunity(&orders, BASE, QUOTE, tick, reference).unwrap();
+        assert_eq!(arbitrage.direction, Direction::Buy);
+        assert_near(arbitrage.base_amount, 1.0);
+        assert_near(arbitrage.quote_amount, 2000.0);
+        assert_near(arbitrage.profit, 100.0);
+    }
+
+    #[test]
+    fn sells_into_bids_above_the_reference() {
+        let orders = book(
+            vec![],
+            vec![
+                order(2100.0, U256::from(4_200_000_000u64)),
+                order(1900.0, U256::from(1_900_000_000u64)),
+            ],
+        );
+        let tick = price_to_tick(2000.0, BASE, QUOTE);
+        let reference = tick_to_price(tick, BASE, QUOTE) as f64;
+        let arbitrage = opportunity(&orders, BASE, QUOTE, tick, reference).unwrap();
+        assert_eq!(arbitrage.direction, Direction::Sell);
+        assert_near(arbitrage.base_amount, 2.0);
+        assert_near(arbitrage.quote_amount, 4200.0);
+        assert_near(arbitrage.profit, 200.0);
+    }
+
+    #[test]
+    fn deviation_is_signed_basis_points() {
+        assert_eq!(deviation_bps(2010.0, 2000.0), 50.0);
+        assert_eq!(deviation_bps(1990.0, 2000.0), -50.0);
+        assert_eq!(deviation_bps(2000.0, 2000.0), 0.0);
+    }
+}
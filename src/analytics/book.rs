@@ -0,0 +1,248 @@
+use crate::commands::lp::{LimitOrder, PoolOrders};
+use crate::util::util::{asset_in_amount, tick_to_price};
+use rust_decimal::prelude::*;
+
+/// Top of book and depth of a pool, with prices and depths in the quote asset.
+#[derive(Clone, Debug)]
+pub struct BookSummary {
+    pub mid: f64,
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+    pub bid_depth_1pct: f64,
+    pub ask_depth_1pct: f64,
+    pub bid_depth_5pct: f64,
+    pub ask_depth_5pct: f64,
+    /// Range order liquidity active at the current tick.
+    pub range_liquidity: f64,
+}
+
+pub fn order_price(order: &LimitOrder, base: &str, quote: &str) -> f64 {
+    tick_to_price(order.tick, base, quote) as f64
+}
+
+/// Value of a limit order in the quote asset. Bids sell the quote asset, asks sell the base.
+pub fn order_value(order: &LimitOrder, base: &str, quote: &str, is_bid: bool) -> f64 {
+    match is_bid {
+        true => asset_in_amount(&order.sell_amount, quote)
+            .to_f64()
+            .unwrap_or_default(),
+        false => {
+            asset_in_amount(&order.sell_amount, base)
+                .to_f64()
+                .unwrap_or_default()
+                * order_price(order, base, quote)
+        }
+    }
+}
+
+pub fn best_bid(orders: &PoolOrders) -> Option<&LimitOrder> {
+    orders
+        .limit_orders
+        .bids
+        .iter()
+        .max_by_key(|order| order.tick)
+}
+
+pub fn best_ask(orders: &PoolOrders) -> Option<&LimitOrder> {
+    orders
+        .limit_orders
+        .asks
+        .iter()
+        .min_by_key(|order| order.tick)
+}
+
+/// Mid of the best bid and ask, falling back to the pool price when a side is empty.
+pub fn mid_price(orders: &PoolOrders, base: &str, quote: &str, current_tick: i32) -> f64 {
+    match (best_bid(orders), best_ask(orders)) {
+        (Some(bid), Some(ask)) => {
+            (order_price(bid, base, quote) + order_price(ask, base, quote)) / 2.0
+        }
+        _ => tick_to_price(current_tick, base, quote) as f64,
+    }
+}
+
+/// Quote value of the bids and asks priced within `band` (e.g. 0.01) of `mid`.
+pub fn depth(orders: &PoolOrders, base: &str, quote: &str, mid: f64, band: f64) -> (f64, f64) {
+    let bids = orders
+        .limit_orders
+        .bids
+        .iter()
+        .filter(|order| order_price(order, base, quote) >= mid * (1.0 - band))
+        .map(|order| order_value(order, base, quote, true))
+        .sum();
+    let asks = orders
+        .limit_orders
+        .asks
+        .iter()
+        .filter(|order| order_price(order, base, quote) <= mid * (1.0 + band))
+        .map(|order| order_value(order, base, quote, false))
+        .sum();
+    (bids, asks)
+}
+
+pub fn summarize(orders: &PoolOrders, base: &str, quote: &str, current_tick: i32) -> BookSummary {
+    let mid = mid_price(orders, base, quote, current_tick);
+    let (bid_depth_1pct, ask_depth_1pct) = depth(orders, base, quote, mid, 0.01);
+    let (bid_depth_5pct, ask_depth_5pct) = depth(orders, base, quote, mid, 0.05);
+    BookSummary {
+        mid,
+        best_bid: best_bid(orders).map(|order| order_price(order, base, quote)),
+        best_ask: best_ask(orders).map(|order| order_price(order, base, quote)),
+        bid_depth_1pct,
+        ask_depth_1pct,
+        bid_depth_5pct,
+        ask_depth_5pct,
+        range_liquidity: orders
+            .range_orders
+            .iter()
+            .filter(|order| order.range.start <= current_tick && current_tick < order.range.end)
+            .map(|order| order.liquidity as f64)
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::lp::{AskBidMap, PoolPairsMap, Range, RangeOrder};
+    use web3::types::U256;
+
+    const BASE: &str = "ETH";
+    const QUOTE: &str = "USDC";
+    /// About 2000 USDC per ETH, a spacing of 100 ticks is about 1%.
+    const TICK: i32 = -200_311;
+
+    fn price(tick: i32) -> f64 {
+        tick_to_price(tick, BASE, QUOTE) as f64
+    }
+
+    fn order(tick: i32, sell_amount: U256) -> LimitOrder {
+        LimitOrder {
+            lp: "cFLp".to_string(),
+            id: U256::zero(),
+            tick,
+            sell_amount,
+            fees_earned: U256::zero(),
+            original_sell_amount: sell_amount,
+        }
+    }
+
+    /// A bid selling 1000 USDC.
+    fn bid(tick: i32) -> LimitOrder {
+        order(tick, U256::from(1_000_000_000u64))
+    }
+
+    /// An ask selling 1 ETH.
+    fn ask(tick: i32) -> LimitOrder {
+        order(tick, U256::exp10(18))
+    }
+
+    fn range(start: i32, end: i32, liquidity: u128) -> RangeOrder {
+        RangeOrder {
+            lp: "cFLp".to_string(),
+            id: U256::zero(),
+            range: Range { start, end },
+            liquidity,
+            fees_earned: PoolPairsMap {
+                base: U256::zero(),
+                quote: U256::zero(),
+            },
+        }
+    }
+
+    fn book(asks: Vec<LimitOrder>, bids: Vec<LimitOrder>) -> PoolOrders {
+        PoolOrders {
+            limit_orders: AskBidMap { asks, bids },
+            range_orders: vec![],
+        }
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected.abs() * 0.001,
+            "{actual} is not within 0.1% of {expected}"
+        );
+    }
+
+    #[test]
+    fn best_orders_are_the_highest_bid_and_lowest_ask() {
+        let orders = book(
+            vec![ask(TICK + 300), ask(TICK + 50), ask(TICK + 1000)],
+            vec![bid(TICK - 300), bid(TICK - 50), bid(TICK - 1000)],
+        );
+        assert_eq!(best_bid(&orders).unwrap().tick, TICK - 50);
+        assert_eq!(best_ask(&orders).unwrap().tick, TICK + 50);
+        assert!(best_bid(&book(vec![], vec![])).is_none());
+        assert!(best_ask(&book(vec![], vec![])).is_none());
+    }
+
+    #[test]
+    fn mid_price_falls_back_to_the_pool_price() {
+        let cases = [
+            (
+                vec![ask(TICK + 50)],
+                vec![bid(TICK - 50)],
+                (price(TICK + 50) + price(TICK - 50)) / 2.0,
+            ),
+            (vec![ask(TICK + 50)], vec![], price(TICK)),
+            (vec![], vec![bid(TICK - 50)], price(TICK)),
+            (vec![], vec![], price(TICK)),
+        ];
+        for (asks, bids, expected) in cases {
+            assert_near(mid_price(&book(asks, bids), BASE, QUOTE, TICK), expected);
+        }
+    }
+
+    #[test]
+    fn order_values_are_in_the_quote_asset() {
+        assert_near(order_value(&bid(TICK), BASE, QUOTE, true), 1000.0);
+        assert_near(order_value(&ask(TICK), BASE, QUOTE, false), price(TICK));
+    }
+
+    #[test]
+    fn depth_only_counts_orders_within_the_band() {
+        let orders = book(
+            vec![ask(TICK + 50), ask(TICK + 300), ask(TICK + 1000)],
+            vec![bid(TICK - 50), bid(TICK - 300), bid(TICK - 1000)],
+        );
+        let mid = price(TICK);
+        let cases = [
+            (0.01, 1000.0, price(TICK + 50)),
+            (0.05, 2000.0, price(TICK + 50) + price(TICK + 300)),
+            (
+                0.5,
+                3000.0,
+                price(TICK + 50) + price(TICK + 300) + price(TICK + 1000),
+            ),
+        ];
+        for (band, bids, asks) in cases {
+            let (bid_depth, ask_depth) = depth(&orders, BASE, QUOTE, mid, band);
+            assert_near(bid_depth, bids);
+            assert_near(ask_depth, asks);
+        }
+    }
+
+    #[test]
+    fn empty_book_summary() {
+        let summary = summarize(&book(vec![], vec![]), BASE, QUOTE, TICK);
+        assert_near(summary.mid, price(TICK));
+        assert!(summary.best_bid.is_none() && summary.best_ask.is_none());
+        assert_eq!(summary.bid_depth_5pct, 0.0);
+        assert_eq!(summary.ask_depth_5pct, 0.0);
+        assert_eq!(summary.range_liquidity, 0.0);
+    }
+
+    #[test]
+    fn range_liquidity_counts_ranges_around_the_current_tick() {
+        let mut orders = book(vec![], vec![]);
+        orders.range_orders = vec![
+            range(TICK - 100, TICK + 100, 1),
+            range(TICK, TICK + 100, 10),
+            // The end of a range is exclusive.
+            range(TICK - 100, TICK, 100),
+            range(TICK + 1, TICK + 100, 1000),
+        ];
+        let summary = summarize(&orders, BASE, QUOTE, TICK);
+        assert_eq!(summary.range_liquidity, 11.0);
+    }
+}
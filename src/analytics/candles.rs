@@ -0,0 +1,150 @@
+use crate::db::snapshots::PoolSnapshot;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Candle {
+    /// Unix timestamp the candle starts at.
+    pub start: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+/// Buckets snapshots (oldest first) into candles of `interval` seconds.
+pub fn build(snapshots: &[PoolSnapshot], interval: i64) -> Vec<Candle> {
+    snapshots
+        .chunk_by(|a, b| a.timestamp.div_euclid(interval) == b.timestamp.div_euclid(interval))
+        .map(|bucket| {
+            let first = &bucket[0];
+            Candle {
+                start: first.timestamp - first.timestamp.rem_euclid(interval),
+                open: first.open,
+                high: bucket.iter().map(|s| s.high).fold(f64::MIN, f64::max),
+                low: bucket.iter().map(|s| s.low).fold(f64::MAX, f64::min),
+                close: bucket[bucket.len() - 1].close,
+            }
+        })
+        .collect()
+}
+
+/// Sample weighted average spread in basis points, over the snapshots with both sides quoted.
+pub fn average_spread_bps(snapshots: &[PoolSnapshot]) -> Option<f64> {
+    let (sum, samples) = snapshots
+        .iter()
+        .filter_map(|s| match (s.best_bid, s.best_ask) {
+            (Some(bid), Some(ask)) if bid + ask > 0.0 => {
+                Some(((ask - bid) / ((ask + bid) / 2.0) * 10_000.0, s.samples))
+            }
+            _ => None,
+        })
+        .fold((0.0, 0), |(sum, n), (bps, w)| (sum + bps * w as f64, n + w));
+    (samples > 0).then(|| sum / samples as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::snapshots::RAW;
+
+    fn snapshot(timestamp: i64, mid: f64, spread: Option<(f64, f64)>) -> PoolSnapshot {
+        PoolSnapshot {
+            base: "ETH".to_string(),
+            quote: "USDC".to_string(),
+            resolution: RAW,
+            timestamp,
+            block: 0,
+            open: mid,
+            high: mid,
+            low: mid,
+            close: mid,
+            best_bid: spread.map(|(bid, _)| bid),
+            best_ask: spread.map(|(_, ask)| ask),
+            bid_depth_1pct: 0.0,
+            ask_depth_1pct: 0.0,
+            bid_depth_5pct: 0.0,
+            ask_depth_5pct: 0.0,
+            range_liquidity: 0.0,
+            samples: 1,
+        }
+    }
+
+    #[test]
+    fn builds_candles_per_interval() {
+        let snapshots = [
+            snapshot(3600, 10.0, None),
+            snapshot(3700, 14.0, None),
+            snapshot(5000, 8.0, None),
+            snapshot(7199, 11.0, None),
+            snapshot(7200, 12.0, None),
+            // A gap leaves no empty candles behind.
+            snapshot(18000, 9.0, None),
+        ];
+        let candles = build(&snapshots, 3600);
+        let expected = [
+            (3600, 10.0, 14.0, 8.0, 11.0),
+            (7200, 12.0, 12.0, 12.0, 12.0),
+            (18000, 9.0, 9.0, 9.0, 9.0),
+        ];
+        assert_eq!(candles.len(), expected.len());
+        for (candle, (start, open, high, low, close)) in candles.iter().zip(expected) {
+            assert_eq!(candle.start, start);
+            assert_eq!(
+                (candle.open, candle.high, candle.low, candle.close),
+                (open, high, low, close)
+            );
+        }
+    }
+
+    #[test]
+    fn candle_starts_are_aligned_to_the_interval() {
+        let cases = [
+            (59, 60, 0),
+            (60, 60, 60),
+            (5000, 3600, 3600),
+            (90_000, 86400, 86400),
+        ];
+        for (timestamp, interval, start) in cases {
+            let candles = build(&[snapshot(timestamp, 1.0, None)], interval);
+            assert_eq!(candles[0].start, start, "{timestamp} at {interval}s");
+        }
+    }
+
+    #[test]
+    fn no_snapshots_no_candles() {
+        assert!(build(&[], 3600).is_empty());
+    }
+
+    #[test]
+    fn average_spread_skips_one_sided_books() {
+        let cases = [
+            (vec![], None),
+            (vec![snapshot(0, 1.0, None)], None),
+            (vec![snapshot(0, 0.0, Some((0.0, 0.0)))], None),
+            (vec![snapshot(0, 1.0, Some((99.0, 101.0)))], Some(200.0)),
+            (
+                vec![
+                    snapshot(0, 1.0, Some((99.0, 101.0))),
+                    snapshot(60, 1.0, None),
+                    snapshot(120, 1.0, Some((99.5, 100.5))),
+                ],
+                Some(150.0),
+            ),
+        ];
+        for (snapshots, expected) in cases {
+            let spread = average_spread_bps(&snapshots);
+            match expected {
+                Some(expected) => assert!((spread.unwrap() - expected).abs() < 1e-9),
+                None => assert!(spread.is_none()),
+            }
+        }
+    }
+
+    #[test]
+    fn average_spread_is_weighted_by_samples() {
+        let mut downsampled = snapshot(0, 1.0, Some((99.0, 101.0)));
+        downsampled.samples = 3;
+        let snapshots = [downsampled, snapshot(60, 1.0, Some((99.5, 100.5)))];
+        let spread = average_spread_bps(&snapshots).unwrap();
+        assert!((spread - 175.0).abs() < 1e-9);
+    }
+}
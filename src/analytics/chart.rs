@@ -0,0 +1,80 @@
+use super::candles::Candle;
+use crate::Error;
+
+const BACKGROUND: [u8; 3] = [0x2b, 0x2d, 0x31];
+const GRID: [u8; 3] = [0x3f, 0x41, 0x47];
+const UP: [u8; 3] = [0x1f, 0x8b, 0x4c];
+const DOWN: [u8; 3] = [0x99, 0x2d, 0x22];
+const PADDING: usize = 16;
+const GRID_LINES: usize = 4;
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: BACKGROUND.repeat(width * height),
+        }
+    }
+
+    fn fill(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, colour: [u8; 3]) {
+        for y in y0.min(y1)..=y0.max(y1).min(self.height - 1) {
+            for x in x0.min(x1)..=x0.max(x1).min(self.width - 1) {
+                let i = (y * self.width + x) * 3;
+                self.pixels[i..i + 3].copy_from_slice(&colour);
+            }
+        }
+    }
+
+    fn encode(self) -> Result<Vec<u8>, Error> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(png)
+    }
+}
+
+/// Renders candles as a candlestick chart PNG. Axis values are left to the embed.
+pub fn candlestick_png(candles: &[Candle], width: usize, height: usize) -> Result<Vec<u8>, Error> {
+    let mut canvas = Canvas::new(width, height);
+    let plot_height = height - 2 * PADDING;
+    for i in 0..=GRID_LINES {
+        let y = PADDING + i * plot_height / GRID_LINES;
+        canvas.fill(PADDING, y, width - PADDING, y, GRID);
+    }
+    if candles.is_empty() {
+        return canvas.encode();
+    }
+    let high = candles.iter().map(|c| c.high).fold(f64::MIN, f64::max);
+    let low = candles.iter().map(|c| c.low).fold(f64::MAX, f64::min);
+    let range = if high > low { high - low } else { 1.0 };
+    let y = |price: f64| PADDING + ((high - price) / range * plot_height as f64).round() as usize;
+    let slot = (width - 2 * PADDING) as f64 / candles.len() as f64;
+    let body_width = ((slot * 0.7) as usize).max(1);
+    for (i, candle) in candles.iter().enumerate() {
+        let colour = if candle.close >= candle.open {
+            UP
+        } else {
+            DOWN
+        };
+        let left = PADDING + (i as f64 * slot + (slot - body_width as f64) / 2.0) as usize;
+        let centre = left + body_width / 2;
+        canvas.fill(centre, y(candle.high), centre, y(candle.low), colour);
+        canvas.fill(
+            left,
+            y(candle.open),
+            left + body_width - 1,
+            y(candle.close),
+            colour,
+        );
+    }
+    canvas.encode()
+}
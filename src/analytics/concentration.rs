@@ -0,0 +1,240 @@
+use super::book::{self, order_value};
+use crate::commands::lp::{LimitOrder, PoolOrders};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Debug, Default)]
+pub struct LpShare {
+    pub lp: String,
+    /// Share of the amount quoted at the best bid and ask ticks.
+    pub top_of_book: f64,
+    /// Share of the limit order depth within 1% of the mid price.
+    pub depth_1pct: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Concentration {
+    /// Sorted by depth share, largest first.
+    pub shares: Vec<LpShare>,
+    /// Herfindahl index of the depth shares, from 0 to 10000.
+    pub hhi: f64,
+    /// LPs with any limit or range order in the pool.
+    pub distinct_lps: usize,
+}
+
+/// The LPs quoting at the best tick of a side.
+pub fn best_quote_holders<'a>(orders: &'a [LimitOrder], best: Option<&LimitOrder>) -> Vec<&'a str> {
+    let Some(best) = best else {
+        return Vec::new();
+    };
+    let holders: BTreeSet<_> = orders
+        .iter()
+        .filter(|order| order.tick == best.tick)
+        .map(|order| order.lp.as_str())
+        .collect();
+    holders.into_iter().collect()
+}
+
+pub fn analyze(orders: &PoolOrders, base: &str, quote: &str, current_tick: i32) -> Concentration {
+    let mid = book::mid_price(orders, base, quote, current_tick);
+    let best_bid = book::best_bid(orders).map(|order| order.tick);
+    let best_ask = book::best_ask(orders).map(|order| order.tick);
+    // LP -> (top of book value, depth value)
+    let mut values: BTreeMap<&str, (f64, f64)> = BTreeMap::new();
+    for (side, is_bid, best) in [
+        (&orders.limit_orders.bids, true, best_bid),
+        (&orders.limit_orders.asks, false, best_ask),
+    ] {
+        for order in side {
+            let value = order_value(order, base, quote, is_bid);
+            let price = book::order_price(order, base, quote);
+            let entry = values.entry(&order.lp).or_default();
+            if Some(order.tick) == best {
+                entry.0 += value;
+            }
+            let in_band = match is_bid {
+                true => price >= mid * 0.99,
+                false => price <= mid * 1.01,
+            };
+            if in_band {
+                entry.1 += value;
+            }
+        }
+    }
+    let (top_total, depth_total) = values
+        .values()
+        .fold((0.0, 0.0), |(t, d), (top, depth)| (t + top, d + depth));
+    let share = |value: f64, total: f64| if total > 0.0 { value / total } else { 0.0 };
+    let mut shares: Vec<LpShare> = values
+        .iter()
+        .map(|(lp, (top, depth))| LpShare {
+            lp: lp.to_string(),
+            top_of_book: share(*top, top_total),
+            depth_1pct: share(*depth, depth_total),
+        })
+        .collect();
+    shares.sort_by(|a, b| b.depth_1pct.total_cmp(&a.depth_1pct));
+    let hhi = shares.iter().map(|s| (s.depth_1pct * 100.0).powi(2)).sum();
+    let distinct_lps = values
+        .keys()
+        .copied()
+        .chain(orders.range_orders.iter().map(|order| order.lp.as_str()))
+        .collect::<BTreeSet<_>>()
+        .len();
+    Concentration {
+        shares,
+        hhi,
+        distinct_lps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::lp::{AskBidMap, PoolPairsMap, Range, RangeOrder};
+    use web3::types::U256;
+
+    const BASE: &str = "ETH";
+    const QUOTE: &str = "USDC";
+    /// About 2000 USDC per ETH, a spacing of 100 ticks is about 1%.
+    const TICK: i32 = -200_311;
+
+    /// A bid selling `usdc` USDC.
+    fn bid(lp: &str, tick: i32, usdc: u64) -> LimitOrder {
+        order(lp, tick, U256::from(usdc) * 1_000_000)
+    }
+
+    /// An ask selling `eth` ETH.
+    fn ask(lp: &str, tick: i32, eth: u64) -> LimitOrder {
+        order(lp, tick, U256::exp10(18) * eth)
+    }
+
+    fn order(lp: &str, tick: i32, sell_amount: U256) -> LimitOrder {
+        LimitOrder {
+            lp: lp.to_string(),
+            id: U256::zero(),
+            tick,
+            sell_amount,
+            fees_earned: U256::zero(),
+            original_sell_amount: sell_amount,
+        }
+    }
+
+    fn book(asks: Vec<LimitOrder>, bids: Vec<LimitOrder>) -> PoolOrders {
+        PoolOrders {
+            limit_orders: AskBidMap { asks, bids },
+            range_orders: vec![],
+        }
+    }
+
+    fn share<'a>(concentration: &'a Concentration, lp: &str) -> &'a LpShare {
+        concentration.shares.iter().find(|s| s.lp == lp).unwrap()
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{actual} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn best_quote_holders_are_the_lps_at_the_best_tick() {
+        let bids = vec![
+            bid("b", TICK - 10, 100),
+            bid("a", TICK - 10, 100),
+            bid("b", TICK - 10, 50),
+            bid("c", TICK - 20, 100),
+        ];
+        let orders = book(vec![], bids);
+        let cases = [
+            (book::best_bid(&orders), vec!["a", "b"]),
+            (book::best_ask(&orders), vec![]),
+        ];
+        for (best, expected) in cases {
+            assert_eq!(
+                best_quote_holders(&orders.limit_orders.bids, best),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn empty_book_has_no_concentration() {
+        let concentration = analyze(&book(vec![], vec![]), BASE, QUOTE, TICK);
+        assert!(concentration.shares.is_empty());
+        assert_eq!(concentration.hhi, 0.0);
+        assert_eq!(concentration.distinct_lps, 0);
+    }
+
+    #[test]
+    fn hhi_of_depth_shares() {
+        let cases = [
+            // A single LP holds everything.
+            (vec![bid("a", TICK - 10, 1000)], 10_000.0),
+            // Two equal LPs.
+            (
+                vec![bid("a", TICK - 10, 1000), bid("b", TICK - 20, 1000)],
+                5000.0,
+            ),
+            // 75% and 25%.
+            (
+                vec![bid("a", TICK - 10, 3000), bid("b", TICK - 10, 1000)],
+                6250.0,
+            ),
+        ];
+        for (bids, hhi) in cases {
+            let concentration = analyze(&book(vec![], bids), BASE, QUOTE, TICK);
+            assert_near(concentration.hhi, hhi);
+        }
+    }
+
+    #[test]
+    fn shares_split_top_of_book_and_depth() {
+        let orders = book(
+            vec![ask("a", TICK + 10, 1)],
+            vec![
+                bid("b", TICK - 10, 2000),
+                // Within 1% of the mid but below the best bid.
+                bid("a", TICK - 50, 2000),
+                // Outside the 1% band, so it counts for neither share.
+                bid("c", TICK - 500, 100_000),
+            ],
+        );
+        let concentration = analyze(&orders, BASE, QUOTE, TICK);
+        assert_eq!(concentration.distinct_lps, 3);
+        // Largest depth share first.
+        assert_eq!(concentration.shares[0].lp, "a");
+        let (a, b, c) = (
+            share(&concentration, "a"),
+            share(&concentration, "b"),
+            share(&concentration, "c"),
+        );
+        // Top of book: 1 ETH (about 2000 USDC) ask for a, the 2000 USDC best bid for b.
+        assert_near(a.top_of_book + b.top_of_book, 1.0);
+        assert_eq!(c.top_of_book, 0.0);
+        assert_eq!(c.depth_1pct, 0.0);
+        assert!(a.depth_1pct > b.depth_1pct);
+        assert_near(a.depth_1pct + b.depth_1pct, 1.0);
+    }
+
+    #[test]
+    fn range_only_lps_count_as_distinct() {
+        let mut orders = book(vec![], vec![bid("a", TICK - 10, 1000)]);
+        orders.range_orders = vec![RangeOrder {
+            lp: "r".to_string(),
+            id: U256::zero(),
+            range: Range {
+                start: TICK - 100,
+                end: TICK + 100,
+            },
+            liquidity: 1,
+            fees_earned: PoolPairsMap {
+                base: U256::zero(),
+                quote: U256::zero(),
+            },
+        }];
+        let concentration = analyze(&orders, BASE, QUOTE, TICK);
+        assert_eq!(concentration.distinct_lps, 2);
+        assert_eq!(concentration.shares.len(), 1);
+    }
+}
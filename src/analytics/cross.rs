@@ -0,0 +1,276 @@
+use super::book::order_price;
+use crate::commands::lp::{LimitOrder, PoolOrders};
+use crate::util::util::asset_in_amount;
+use rust_decimal::prelude::*;
+
+/// Rate of one A in B, through the A/quote and B/quote pools.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrossRate {
+    /// Selling A for B.
+    pub bid: Option<f64>,
+    /// Buying A with B.
+    pub ask: Option<f64>,
+}
+
+impl CrossRate {
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.bid? + self.ask?) / 2.0)
+    }
+
+    /// Spread between the bid and ask in basis points.
+    pub fn spread_bps(&self) -> Option<f64> {
+        let mid = self.mid().filter(|mid| *mid > 0.0)?;
+        Some((self.ask? - self.bid?) / mid * 10_000.0)
+    }
+}
+
+/// Levels of a side as (price, base amount available), best first. Prices too small to
+/// represent are left out.
+fn levels(orders: &PoolOrders, base: &str, quote: &str, bids: bool) -> Vec<(f64, f64)> {
+    let amount = |order: &LimitOrder, asset: &str| {
+        asset_in_amount(&order.sell_amount, asset)
+            .to_f64()
+            .unwrap_or_default()
+    };
+    let mut levels: Vec<(f64, f64)> = match bids {
+        true => orders
+            .limit_orders
+            .bids
+            .iter()
+            .map(|order| {
+                let price = order_price(order, base, quote);
+                (price, amount(order, quote) / price)
+            })
+            .collect(),
+        false => orders
+            .limit_orders
+            .asks
+            .iter()
+            .map(|order| (order_price(order, base, quote), amount(order, base)))
+            .collect(),
+    };
+    levels.retain(|(price, _)| *price > 0.0);
+    match bids {
+        true => levels.sort_by(|a, b| b.0.total_cmp(&a.0)),
+        false => levels.sort_by(|a, b| a.0.total_cmp(&b.0)),
+    }
+    levels
+}
+
+/// Quote amount for trading `base_amount` through the levels, or `None` without enough depth.
+fn fill_base(levels: &[(f64, f64)], mut base_amount: f64) -> Option<f64> {
+    let mut quote_amount = 0.0;
+    for (price, available) in levels {
+        let filled = base_amount.min(*available);
+        quote_amount += filled * price;
+        base_amount -= filled;
+        if base_amount <= 0.0 {
+            return Some(quote_amount);
+        }
+    }
+    None
+}
+
+/// Base amount for trading `quote_amount` through the levels, or `None` without enough depth.
+fn fill_quote(levels: &[(f64, f64)], mut quote_amount: f64) -> Option<f64> {
+    let mut base_amount = 0.0;
+    for (price, available) in levels {
+        let filled = (quote_amount / price).min(*available);
+        base_amount += filled;
+        quote_amount -= filled * price;
+        if quote_amount <= 1e-12 {
+            return Some(base_amount);
+        }
+    }
+    None
+}
+
+/// Cross rate at the top of both books.
+pub fn top_of_book(
+    a: &PoolOrders,
+    b: &PoolOrders,
+    a_asset: &str,
+    b_asset: &str,
+    quote: &str,
+) -> CrossRate {
+    let best = |orders, asset, bids| levels(orders, asset, quote, bids).first().map(|l| l.0);
+    let rate = |a_price: Option<f64>, b_price: Option<f64>| Some(a_price? / b_price?);
+    // Levels only hold positive prices, so the division is safe.
+    CrossRate {
+        bid: rate(best(a, a_asset, true), best(b, b_asset, false)),
+        ask: rate(best(a, a_asset, false), best(b, b_asset, true)),
+    }
+}
+
+/// Average cross rate for trading `size` of A, walking both books. Empty for a size of zero.
+pub fn for_size(
+    a: &PoolOrders,
+    b: &PoolOrders,
+    a_asset: &str,
+    b_asset: &str,
+    quote: &str,
+    size: f64,
+) -> CrossRate {
+    if size <= 0.0 {
+        return CrossRate::default();
+    }
+    // Selling A: A into the A bids for quote, then quote into the B asks for B.
+    let bid = fill_base(&levels(a, a_asset, quote, true), size)
+        .and_then(|quote_amount| fill_quote(&levels(b, b_asset, quote, false), quote_amount));
+    // Buying A: quote from the A asks, paid for by selling B into the B bids.
+    let ask = fill_base(&levels(a, a_asset, quote, false), size)
+        .and_then(|quote_amount| fill_quote(&levels(b, b_asset, quote, true), quote_amount));
+    CrossRate {
+        bid: bid.map(|b_amount| b_amount / size),
+        ask: ask.map(|b_amount| b_amount / size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::lp::AskBidMap;
+    use crate::util::util::{price_to_tick, tick_to_price};
+    use web3::types::U256;
+
+    const QUOTE: &str = "USDC";
+
+    fn price(price: f64, asset: &str) -> f64 {
+        tick_to_price(price_to_tick(price, asset, QUOTE), asset, QUOTE) as f64
+    }
+
+    fn order(price: f64, asset: &str, sell_amount: U256) -> LimitOrder {
+        LimitOrder {
+            lp: "cFLp".to_string(),
+            id: U256::zero(),
+            tick: price_to_tick(price, asset, QUOTE),
+            sell_amount,
+            fees_earned: U256::zero(),
+            original_sell_amount: sell_amount,
+        }
+    }
+
+    /// A book of `asset` against USDC, with levels of (price, asset amount) on each side.
+    fn book(asset: &str, asks: &[(f64, u64)], bids: &[(f64, u64)]) -> PoolOrders {
+        let decimals = match asset {
+            "ETH" => 18,
+            _ => 8,
+        };
+        let units = |amount: u64| U256::from(amount) * U256::exp10(decimals);
+        let usdc = |amount: f64| U256::from((amount * 1e6) as u128);
+        PoolOrders {
+            limit_orders: AskBidMap {
+                asks: asks
+                    .iter()
+                    .map(|(p, amount)| order(*p, asset, units(*amount)))
+                    .collect(),
+                bids: bids
+                    .iter()
+                    .map(|(p, amount)| order(*p, asset, usdc(price(*p, asset) * *amount as f64)))
+                    .collect(),
+            },
+            range_orders: vec![],
+        }
+    }
+
+    fn empty() -> PoolOrders {
+        book("ETH", &[], &[])
+    }
+
+    fn assert_near(actual: Option<f64>, expected: f64) {
+        let actual = actual.unwrap();
+        assert!(
+            (actual - expected).abs() <= expected.abs() * 0.005,
+            "{actual} is not within 0.5% of {expected}"
+        );
+    }
+
+    #[test]
+    fn top_of_book_crosses_through_the_quote_asset() {
+        let eth = book(
+            "ETH",
+            &[(2010.0, 1), (2100.0, 1)],
+            &[(1990.0, 1), (1900.0, 1)],
+        );
+        let btc = book("BTC", &[(40_100.0, 1)], &[(39_900.0, 1)]);
+        let rate = top_of_book(&eth, &btc, "ETH", "BTC", QUOTE);
+        // Sell ETH at the best ETH bid, buy BTC at the best BTC ask, and the other way around.
+        assert_near(rate.bid, 1990.0 / 40_100.0);
+        assert_near(rate.ask, 2010.0 / 39_900.0);
+        assert!(rate.spread_bps().unwrap() > 0.0);
+    }
+
+    #[test]
+    fn empty_books_have_no_rate() {
+        let eth = book("ETH", &[(2010.0, 1)], &[(1990.0, 1)]);
+        let cases = [
+            (empty(), eth.clone()),
+            (eth.clone(), empty()),
+            (empty(), empty()),
+        ];
+        for (a, b) in cases {
+            let rate = top_of_book(&a, &b, "ETH", "ETH", QUOTE);
+            assert!(rate.bid.is_none() && rate.ask.is_none());
+            assert!(rate.mid().is_none() && rate.spread_bps().is_none());
+            let rate = for_size(&a, &b, "ETH", "ETH", QUOTE, 1.0);
+            assert!(rate.bid.is_none() && rate.ask.is_none());
+        }
+    }
+
+    #[test]
+    fn for_size_walks_the_books() {
+        let eth = book(
+            "ETH",
+            &[(2000.0, 1), (2200.0, 1)],
+            &[(2000.0, 1), (1800.0, 1)],
+        );
+        let btc = book("BTC", &[(40_000.0, 10)], &[(40_000.0, 10)]);
+        let cases = [
+            (0.5, 2000.0 / 40_000.0, 2000.0 / 40_000.0),
+            // Half of it at the second level on each side.
+            (1.5, 5800.0 / 3.0 / 40_000.0, 6200.0 / 3.0 / 40_000.0),
+        ];
+        for (size, bid, ask) in cases {
+            let rate = for_size(&eth, &btc, "ETH", "BTC", QUOTE, size);
+            assert_near(rate.bid, bid);
+            assert_near(rate.ask, ask);
+        }
+    }
+
+    #[test]
+    fn for_size_needs_enough_depth() {
+        let eth = book("ETH", &[(2000.0, 1)], &[(2000.0, 1)]);
+        let btc = book("BTC", &[(40_000.0, 10)], &[(40_000.0, 10)]);
+        let rate = for_size(&eth, &btc, "ETH", "BTC", QUOTE, 3.0);
+        assert!(rate.bid.is_none() && rate.ask.is_none());
+        // Enough ETH, but not enough BTC on the other leg.
+        let btc = book("BTC", &[(40_000.0, 1)], &[(40_000.0, 1)]);
+        let eth = book("ETH", &[(2000.0, 100)], &[(2000.0, 100)]);
+        let rate = for_size(&eth, &btc, "ETH", "BTC", QUOTE, 50.0);
+        assert!(rate.bid.is_none() && rate.ask.is_none());
+    }
+
+    #[test]
+    fn zero_size_has_no_rate() {
+        let eth = book("ETH", &[(2000.0, 1)], &[(2000.0, 1)]);
+        let rate = for_size(&eth, &eth, "ETH", "ETH", QUOTE, 0.0);
+        assert!(rate.bid.is_none() && rate.ask.is_none());
+    }
+
+    #[test]
+    fn spread_of_rates() {
+        let cases = [
+            (Some(0.99), Some(1.01), Some(200.0)),
+            (Some(1.0), Some(1.0), Some(0.0)),
+            (Some(0.0), Some(0.0), None),
+            (None, Some(1.0), None),
+        ];
+        for (bid, ask, expected) in cases {
+            let spread = CrossRate { bid, ask }.spread_bps();
+            match expected {
+                Some(expected) => assert!((spread.unwrap() - expected).abs() < 1e-9),
+                None => assert!(spread.is_none()),
+            }
+        }
+    }
+}
@@ -0,0 +1,8 @@
+pub mod arbitrage;
+pub mod book;
+pub mod candles;
+pub mod chart;
+pub mod concentration;
+pub mod cross;
+pub mod range;
+pub mod volume;
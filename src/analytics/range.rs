@@ -0,0 +1,199 @@
+use crate::commands::lp::{Range, RangeOrder};
+
+// Concentrated liquidity math. Amounts and prices are in atomic units, so a tick's price is
+// simply 1.0001^tick quote per base.
+
+pub fn sqrt_price_at(tick: i32) -> f64 {
+    1.0001_f64.powi(tick).sqrt()
+}
+
+fn sqrt_bounds(range: &Range) -> (f64, f64) {
+    (sqrt_price_at(range.start), sqrt_price_at(range.end))
+}
+
+/// Liquidity a position over `range` gets from the given amounts at `sqrt_price`, limited by
+/// whichever asset runs out first.
+pub fn liquidity_for_amounts(range: &Range, sqrt_price: f64, base: f64, quote: f64) -> f64 {
+    let (lower, upper) = sqrt_bounds(range);
+    if lower >= upper {
+        0.0
+    } else if sqrt_price <= lower {
+        base * lower * upper / (upper - lower)
+    } else if sqrt_price >= upper {
+        quote / (upper - lower)
+    } else {
+        let from_base = base * sqrt_price * upper / (upper - sqrt_price);
+        let from_quote = quote / (sqrt_price - lower);
+        from_base.min(from_quote)
+    }
+}
+
+/// Share of the range fees `liquidity` earns when added to `pool_liquidity`.
+pub fn share(liquidity: f64, pool_liquidity: f64) -> f64 {
+    match liquidity > 0.0 {
+        true => liquidity / (pool_liquidity + liquidity),
+        false => 0.0,
+    }
+}
+
+/// Base and quote amounts a range order holds at `sqrt_price`.
+pub fn amounts(order: &RangeOrder, sqrt_price: f64) -> (f64, f64) {
+    let (lower, upper) = sqrt_bounds(&order.range);
+    let sqrt_price = sqrt_price.clamp(lower, upper);
+    let liquidity = order.liquidity as f64;
+    (
+        liquidity * (upper - sqrt_price) / (sqrt_price * upper),
+        liquidity * (sqrt_price - lower),
+    )
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PriceMove {
+    /// Relative price change, e.g. -0.1 for a 10% drop.
+    pub change: f64,
+    /// Value of the position after the move, in atomic quote units.
+    pub position_value: f64,
+    /// Value of holding the initial amounts instead.
+    pub hold_value: f64,
+}
+
+impl PriceMove {
+    /// Zero for an empty position.
+    pub fn impermanent_loss(&self) -> f64 {
+        match self.hold_value > 0.0 {
+            true => self.position_value / self.hold_value - 1.0,
+            false => 0.0,
+        }
+    }
+}
+
+/// Compares the position against holding its initial amounts for each relative price move.
+pub fn price_moves(order: &RangeOrder, tick: i32, changes: &[f64]) -> Vec<PriceMove> {
+    let sqrt_price = sqrt_price_at(tick);
+    let (base, quote) = amounts(order, sqrt_price);
+    changes
+        .iter()
+        .map(|change| {
+            let price = sqrt_price * sqrt_price * (1.0 + change);
+            let (new_base, new_quote) = amounts(order, price.sqrt());
+            PriceMove {
+                change: *change,
+                position_value: new_base * price + new_quote,
+                hold_value: base * price + quote,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::lp::PoolPairsMap;
+    use web3::types::U256;
+
+    fn position(start: i32, end: i32, liquidity: u128) -> RangeOrder {
+        RangeOrder {
+            lp: String::new(),
+            id: U256::zero(),
+            range: Range { start, end },
+            liquidity,
+            fees_earned: PoolPairsMap {
+                base: U256::zero(),
+                quote: U256::zero(),
+            },
+        }
+    }
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected.abs() * 1e-6 + 1e-9,
+            "{actual} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn sqrt_price_of_ticks() {
+        assert_eq!(sqrt_price_at(0), 1.0);
+        assert_near(sqrt_price_at(200), 1.0001_f64.powi(100));
+        assert_near(sqrt_price_at(-200), 1.0 / 1.0001_f64.powi(100));
+    }
+
+    #[test]
+    fn liquidity_round_trips_through_amounts() {
+        let order = position(-1000, 1000, 1_000_000_000);
+        // Below, inside and above the range.
+        for tick in [-2000, -1000, -500, 0, 500, 1000, 2000] {
+            let sqrt_price = sqrt_price_at(tick);
+            let (base, quote) = amounts(&order, sqrt_price);
+            let liquidity = liquidity_for_amounts(&order.range, sqrt_price, base, quote);
+            assert_near(liquidity, order.liquidity as f64);
+        }
+    }
+
+    #[test]
+    fn amounts_are_one_sided_outside_the_range() {
+        let order = position(-1000, 1000, 1_000_000_000);
+        let cases = [(-2000, true, false), (0, true, true), (2000, false, true)];
+        for (tick, has_base, has_quote) in cases {
+            let (base, quote) = amounts(&order, sqrt_price_at(tick));
+            assert_eq!(
+                (base > 0.0, quote > 0.0),
+                (has_base, has_quote),
+                "at tick {tick}"
+            );
+        }
+    }
+
+    #[test]
+    fn liquidity_is_limited_by_the_scarcer_asset() {
+        let range = Range {
+            start: -1000,
+            end: 1000,
+        };
+        let balanced = liquidity_for_amounts(&range, 1.0, 1000.0, 1000.0);
+        assert_near(liquidity_for_amounts(&range, 1.0, 1000.0, 1e9), balanced);
+        assert_near(liquidity_for_amounts(&range, 1.0, 1e9, 1000.0), balanced);
+        assert_eq!(liquidity_for_amounts(&range, 1.0, 0.0, 1000.0), 0.0);
+    }
+
+    #[test]
+    fn empty_ranges_hold_no_liquidity() {
+        let range = Range { start: 10, end: 10 };
+        for sqrt_price in [0.5, sqrt_price_at(10), 2.0] {
+            assert_eq!(liquidity_for_amounts(&range, sqrt_price, 1.0, 1.0), 0.0);
+        }
+    }
+
+    #[test]
+    fn share_of_pool_liquidity() {
+        let cases = [
+            (0.0, 0.0, 0.0),
+            (0.0, 100.0, 0.0),
+            (100.0, 0.0, 1.0),
+            (100.0, 300.0, 0.25),
+        ];
+        for (liquidity, pool_liquidity, expected) in cases {
+            assert_eq!(share(liquidity, pool_liquidity), expected);
+        }
+    }
+
+    #[test]
+    fn impermanent_loss_of_price_moves() {
+        let order = position(-1000, 1000, 1_000_000_000);
+        let moves = price_moves(&order, 0, &[-0.05, 0.0, 0.05]);
+        assert_eq!(moves.len(), 3);
+        assert_near(moves[1].impermanent_loss(), 0.0);
+        // A position in range always trails holding when the price moves either way.
+        assert!(moves[0].impermanent_loss() < 0.0);
+        assert!(moves[2].impermanent_loss() < 0.0);
+    }
+
+    #[test]
+    fn empty_positions_have_no_impermanent_loss() {
+        let moves = price_moves(&position(-1000, 1000, 0), 0, &[-0.5, 0.5]);
+        for price_move in moves {
+            assert_eq!(price_move.hold_value, 0.0);
+            assert_eq!(price_move.impermanent_loss(), 0.0);
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::commands::lp::{LimitOrder, PoolOrders};
+use crate::util::util::asset_in_amount;
+use rust_decimal::prelude::*;
+use std::collections::HashMap;
+use web3::types::U256;
+
+/// Fills inferred between two order book snapshots, valued in the quote asset.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fills {
+    /// Volume that filled limit orders.
+    pub volume: f64,
+    pub limit_fees: f64,
+    pub range_fees: f64,
+}
+
+impl Fills {
+    pub fn fees(&self) -> f64 {
+        self.limit_fees + self.range_fees
+    }
+}
+
+fn amount(value: U256, asset: &str) -> f64 {
+    asset_in_amount(&value, asset).to_f64().unwrap_or_default()
+}
+
+fn by_id(orders: &[LimitOrder]) -> HashMap<(&str, U256), &LimitOrder> {
+    orders
+        .iter()
+        .map(|order| ((order.lp.as_str(), order.id), order))
+        .collect()
+}
+
+/// Whether the pool price moved from `from` to `to` through `tick`, filling the orders there.
+/// It rises through asks and falls through bids.
+fn moved_through(tick: i32, from: i32, to: i32, is_ask: bool) -> bool {
+    match is_ask {
+        true => from < tick && tick <= to,
+        false => to <= tick && tick < from,
+    }
+}
+
+/// Estimates fills from how much sold and how many fees were earned between `prev` and
+/// `next`, each an order book with the pool tick it was read at. `price` converts base amounts
+/// to the quote asset.
+///
+/// Orders that disappear were either cancelled or filled, which the books can't tell apart.
+/// One the pool price moved through is taken as filled for its last remaining amount, any
+/// other as cancelled. The fees a vanished order earned on its last fill aren't known, so this
+/// errs on the low side.
+pub fn estimate(
+    (prev, prev_tick): (&PoolOrders, i32),
+    (next, next_tick): (&PoolOrders, i32),
+    base: &str,
+    quote: &str,
+    price: f64,
+) -> Fills {
+    let mut fills = Fills::default();
+    // Asks sell the base asset and earn fees in the quote asset, bids the other way around.
+    for (prev_orders, next_orders, sold_asset, fee_asset, is_ask) in [
+        (
+            &prev.limit_orders.asks,
+            &next.limit_orders.asks,
+            base,
+            quote,
+            true,
+        ),
+        (
+            &prev.limit_orders.bids,
+            &next.limit_orders.bids,
+            quote,
+            base,
+            false,
+        ),
+    ] {
+        let to_quote = |value: f64, asset: &str| match asset == base {
+            true => value * price,
+            false => value,
+        };
+        let next_orders = by_id(next_orders);
+        for prev_order in prev_orders {
+            let (sold, fees) = match next_orders.get(&(prev_order.lp.as_str(), prev_order.id)) {
+                Some(order) => (
+                    prev_order.sell_amount.saturating_sub(order.sell_amount),
+                    order.fees_earned.saturating_sub(prev_order.fees_earned),
+                ),
+                None if moved_through(prev_order.tick, prev_tick, next_tick, is_ask) => {
+                    (prev_order.sell_amount, U256::zero())
+                }
+                None => continue,
+            };
+            fills.volume += to_quote(amount(sold, sold_asset), sold_asset);
+            fills.limit_fees += to_quote(amount(fees, fee_asset), fee_asset);
+        }
+    }
+    let prev_ranges: HashMap<_, _> = prev
+        .range_orders
+        .iter()
+        .map(|order| ((order.lp.as_str(), order.id), order))
+        .collect();
+    for order in &next.range_orders {
+        let Some(prev_order) = prev_ranges.get(&(order.lp.as_str(), order.id)) else {
+            continue;
+        };
+        let base_fees = order
+            .fees_earned
+            .base
+            .saturating_sub(prev_order.fees_earned.base);
+        let quote_fees = order
+            .fees_earned
+            .quote
+            .saturating_sub(prev_order.fees_earned.quote);
+        fills.range_fees += amount(base_fees, base) * price + amount(quote_fees, quote);
+    }
+    fills
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::lp::{AskBidMap, PoolPairsMap, Range, RangeOrder};
+
+    const BASE: &str = "ETH";
+    const QUOTE: &str = "USDC";
+    const PRICE: f64 = 2000.0;
+    const TICK: i32 = -200_311;
+
+    fn eth(amount: u64) -> U256 {
+        U256::exp10(18) * amount
+    }
+
+    fn usdc(amount: u64) -> U256 {
+        U256::from(amount) * 1_000_000
+    }
+
+    fn order(id: u64, tick: i32, sell_amount: U256, fees_earned: U256) -> LimitOrder {
+        LimitOrder {
+            lp: "cFLp".to_string(),
+            id: U256::from(id),
+            tick,
+            sell_amount,
+            fees_earned,
+            original_sell_amount: sell_amount,
+        }
+    }
+
+    fn book(asks: Vec<LimitOrder>, bids: Vec<LimitOrder>) -> PoolOrders {
+        PoolOrders {
+            limit_orders: AskBidMap { asks, bids },
+            range_orders: vec![],
+        }
+    }
+
+    fn range(id: u64, base_fees: U256, quote_fees: U256) -> RangeOrder {
+        RangeOrder {
+            lp: "cFLp".to_string(),
+            id: U256::from(id),
+            range: Range {
+                start: TICK - 100,
+                end: TICK + 100,
+            },
+            liquidity: 1,
+            fees_earned: PoolPairsMap {
+                base: base_fees,
+                quote: quote_fees,
+            },
+        }
+    }
+
+    fn estimate_at(prev: &PoolOrders, next: &PoolOrders, prev_tick: i32, next_tick: i32) -> Fills {
+        estimate((prev, prev_tick), (next, next_tick), BASE, QUOTE, PRICE)
+    }
+
+    fn assert_fills(fills: Fills, volume: f64, limit_fees: f64, range_fees: f64) {
+        assert!(
+            (fills.volume - volume).abs() < 1e-9
+                && (fills.limit_fees - limit_fees).abs() < 1e-9
+                && (fills.range_fees - range_fees).abs() < 1e-9,
+            "{fills:?} != ({volume}, {limit_fees}, {range_fees})"
+        );
+    }
+
+    #[test]
+    fn partial_fills_count_what_sold_and_the_fees_earned() {
+        let prev = book(
+            vec![order(1, TICK + 10, eth(2), usdc(0))],
+            vec![order(2, TICK - 10, usdc(4000), eth(0))],
+        );
+        let next = book(
+            vec![order(1, TICK + 10, eth(1), usdc(1))],
+            vec![order(2, TICK - 10, usdc(1000), eth(1) / 1000)],
+        );
+        // 1 ETH sold by the ask and 3000 USDC by the bid, fees of 1 USDC and 0.001 ETH.
+        assert_fills(estimate_at(&prev, &next, TICK, TICK), 5000.0, 3.0, 0.0);
+    }
+
+    #[test]
+    fn unchanged_and_new_orders_count_nothing() {
+        let prev = book(vec![order(1, TICK + 10, eth(1), usdc(0))], vec![]);
+        let next = book(
+            vec![
+                order(1, TICK + 10, eth(1), usdc(0)),
+                order(2, TICK + 10, eth(5), usdc(0)),
+            ],
+            vec![order(3, TICK - 10, usdc(1000), eth(0))],
+        );
+        assert_fills(estimate_at(&prev, &next, TICK, TICK), 0.0, 0.0, 0.0);
+    }
+
+    #[test]
+    fn empty_books_count_nothing() {
+        let empty = book(vec![], vec![]);
+        assert_fills(estimate_at(&empty, &empty, TICK, TICK), 0.0, 0.0, 0.0);
+    }
+
+    /// A fully filled order disappears from the book like a cancelled one. It only counts when
+    /// the price moved through its tick, and then without the fees of its last fill.
+    #[test]
+    fn vanished_orders_count_when_the_price_moved_through_them() {
+        let ask = order(1, TICK + 10, eth(1), usdc(5));
+        let bid = order(2, TICK - 10, usdc(1000), eth(0));
+        let prev = book(vec![ask], vec![bid]);
+        let next = book(vec![], vec![]);
+        let cases = [
+            // Price unchanged: both cancelled.
+            (TICK, TICK, 0.0),
+            // Rose through the ask, or up to exactly its tick.
+            (TICK, TICK + 20, 2000.0),
+            (TICK, TICK + 10, 2000.0),
+            // Fell through the bid.
+            (TICK, TICK - 20, 1000.0),
+            // Moved, but not as far as either order.
+            (TICK, TICK + 5, 0.0),
+            (TICK, TICK - 5, 0.0),
+            // Already past the ask before, so it didn't fill in between.
+            (TICK + 10, TICK + 20, 0.0),
+        ];
+        for (prev_tick, next_tick, volume) in cases {
+            let fills = estimate_at(&prev, &next, prev_tick, next_tick);
+            assert_fills(fills, volume, 0.0, 0.0);
+        }
+    }
+
+    #[test]
+    fn range_fees_are_valued_in_the_quote_asset() {
+        let mut prev = book(vec![], vec![]);
+        prev.range_orders = vec![range(1, eth(0), usdc(10)), range(2, eth(0), usdc(0))];
+        let mut next = book(vec![], vec![]);
+        next.range_orders = vec![
+            range(1, eth(1) / 100, usdc(15)),
+            // Closed ranges count nothing, nor do new ones.
+            range(3, eth(1), usdc(100)),
+        ];
+        assert_fills(estimate_at(&prev, &next, TICK, TICK), 0.0, 0.0, 25.0);
+    }
+}
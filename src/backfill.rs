@@ -0,0 +1,104 @@
+use crate::db::Db;
+use crate::rpc::block_at::{self, BlockAt};
+use crate::rpc::pool::RpcPool;
+use crate::tasks::recorder::Recorder;
+use crate::Error;
+
+const USAGE: &str =
+    "usage: jitcord backfill --from <block> --to <block> [--step <blocks>] [--archive <url>]";
+
+#[derive(Clone, Debug)]
+pub struct BackfillArgs {
+    pub from: u32,
+    pub to: u32,
+    pub step: u32,
+    /// Archive endpoint, defaults to `JITCORD_ARCHIVE_TARGET`.
+    pub archive: Option<String>,
+}
+
+impl BackfillArgs {
+    /// Parses the arguments following `backfill`.
+    pub fn parse(args: &[String]) -> Result<BackfillArgs, Error> {
+        let (mut from, mut to, mut step, mut archive) = (None, None, 1, None);
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or(format!("missing value for {flag}\n{USAGE}"))?;
+            let block = || {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid value for {flag}: {value}\n{USAGE}"))
+            };
+            match flag.as_str() {
+                "--from" => from = Some(block()?),
+                "--to" => to = Some(block()?),
+                "--step" => step = block()?.max(1),
+                "--archive" => archive = Some(value.clone()),
+                _ => return Err(format!("unknown argument: {flag}\n{USAGE}").into()),
+            }
+        }
+        match (from, to) {
+            (Some(from), Some(to)) if from <= to => Ok(BackfillArgs {
+                from,
+                to,
+                step,
+                archive,
+            }),
+            _ => Err(USAGE.into()),
+        }
+    }
+
+    // Identifies the run, so an interrupted one resumes instead of starting over.
+    fn checkpoint_key(&self) -> String {
+        format!("{}-{}-{}", self.from, self.to, self.step)
+    }
+}
+
+/// Replays pool and auction state at historical blocks into the recorder's store.
+pub async fn run(
+    args: BackfillArgs,
+    rpc: RpcPool,
+    assets: Vec<String>,
+    db: Db,
+) -> Result<(), Error> {
+    let key = args.checkpoint_key();
+    let start = match db.backfill_checkpoint(&key)? {
+        Some(next_block) => {
+            println!("resuming backfill {key} at block {next_block}");
+            next_block
+        }
+        None => args.from,
+    };
+    let mut recorder = Recorder::new(assets);
+    // Fills are inferred against the previous block, which on resume was recorded before.
+    if let Some(previous) = start.checked_sub(args.step).filter(|_| start > args.from) {
+        let at = BlockAt::resolve(&rpc, Some(previous)).await?;
+        recorder
+            .seed(&rpc, previous, at.hash.ok_or("block hash not resolved")?)
+            .await;
+    }
+    for block in (start..=args.to).step_by(args.step as usize) {
+        let at = BlockAt::resolve(&rpc, Some(block)).await?;
+        let hash = at.hash.ok_or("block hash not resolved")?;
+        let timestamp = block_at::block_timestamp(&rpc, hash)
+            .await
+            .map_err(|err| at.request_error(err))?;
+        recorder
+            .record(&rpc, &db, block, Some(hash), timestamp)
+            .await
+            .map_err(|err| at.request_error(err))?;
+        // Every response is for a distinct block, so there is nothing worth keeping cached.
+        rpc.invalidate_block_cache();
+        // Past the last block a u32 holds there's nothing left to resume.
+        if let Some(next_block) = block.checked_add(args.step) {
+            db.set_backfill_checkpoint(&key, next_block)?;
+        }
+        if ((block - args.from) / args.step).is_multiple_of(100) {
+            println!("backfilled block {block} of {}", args.to);
+        }
+    }
+    db.clear_backfill_checkpoint(&key)?;
+    println!("backfill {key} complete");
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use crate::config::{RegistrationMode, RegistrationSettings};
+use crate::{Context, Data, Error};
+use poise::serenity_prelude::{self as serenity, GuildId, Http};
+use std::collections::BTreeSet;
+
+type Command = poise::Command<Data, Error>;
+
+/// Bot maintenance, for the bot's owners
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("register"),
+    subcommand_required,
+    owners_only,
+    hide_in_help,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn admin(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Re-syncs slash commands with Discord as configured
+///
+/// Also works as a prefix command, e.g. `@jitcord admin register`, while no slash commands are
+/// registered yet.
+#[poise::command(prefix_command, slash_command)]
+pub async fn register(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let commands = &ctx.framework().options().commands;
+    let response = sync(ctx.http(), commands, &ctx.data().registration).await?;
+    ctx.send(
+        poise::CreateReply::default()
+            .content(response)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Registers the slash commands, with experimental groups only in their guilds. Guilds that
+/// were dropped from the config keep their commands until they are cleared by hand, and guilds
+/// that fail are logged and left for the next sync.
+pub async fn sync(
+    http: &Http,
+    commands: &[Command],
+    settings: &RegistrationSettings,
+) -> Result<String, Error> {
+    let experimental = |command: &Command, guild: Option<u64>| {
+        settings
+            .experimental
+            .iter()
+            .find(|(group, _)| *group == command.name)
+            .map(|(_, guilds)| guild.is_some_and(|guild| guilds.contains(&guild)))
+    };
+    let create = |filter: &dyn Fn(&Command) -> bool| {
+        commands
+            .iter()
+            .filter(|command| filter(command))
+            .filter_map(|command| command.create_as_slash_command())
+            .collect::<Vec<_>>()
+    };
+
+    let mut guilds = settings
+        .experimental
+        .iter()
+        .flat_map(|(_, guilds)| guilds.iter().copied())
+        .collect::<BTreeSet<_>>();
+    // `Off` only skips registering on startup, asking for it registers globally.
+    let global = match settings.mode {
+        RegistrationMode::Guilds => {
+            guilds.extend(&settings.guilds);
+            Vec::new()
+        }
+        RegistrationMode::Global | RegistrationMode::Off => {
+            create(&|command| experimental(command, None).is_none())
+        }
+    };
+    let global_count = global.len();
+    serenity::Command::set_global_commands(http, global).await?;
+
+    let mut failed = Vec::new();
+    for &guild in &guilds {
+        let in_guild = create(&|command| match experimental(command, Some(guild)) {
+            Some(enabled) => enabled,
+            None => settings.mode == RegistrationMode::Guilds && settings.guilds.contains(&guild),
+        });
+        // A guild the bot was removed from, or can't register in, shouldn't hold up the rest.
+        if let Err(err) = GuildId::new(guild).set_commands(http, in_guild).await {
+            eprintln!("admin: registering commands in guild {guild}: {err}");
+            failed.push(guild);
+        }
+    }
+    let mut response = format!(
+        "Registered {} commands globally and synced {} servers",
+        global_count,
+        guilds.len() - failed.len()
+    );
+    if !failed.is_empty() {
+        let failed = failed.iter().map(u64::to_string).collect::<Vec<_>>();
+        response += &format!(", failed in {}", failed.join(", "));
+    }
+    Ok(response)
+}
+
+/// Whether a command group is usable in the caller's guild, which is only limited for
+/// experimental groups.
+pub fn enabled_here(ctx: Context<'_>, group: &str) -> bool {
+    let experimental = ctx
+        .data()
+        .registration
+        .experimental
+        .iter()
+        .find(|(name, _)| name == group);
+    match experimental {
+        Some((_, guilds)) => ctx
+            .guild_id()
+            .is_some_and(|guild| guilds.contains(&guild.get())),
+        None => true,
+    }
+}
+
+/// Rejects experimental groups outside the guilds they are enabled in, which matters for
+/// prefix commands since those aren't registered anywhere.
+pub fn group_enabled(ctx: Context<'_>) -> Result<bool, Error> {
+    let group = ctx.command().qualified_name.split(' ').next().unwrap_or("");
+    match enabled_here(ctx, group) {
+        true => Ok(true),
+        false => Err(format!("`/{}` isn't enabled in this server", group).into()),
+    }
+}
@@ -0,0 +1,161 @@
+use crate::commands::cf::{
+    search_account_by_name, search_accounts_by_name, AccountInfo, AccountList,
+};
+use crate::{Context, Error};
+use jsonrpsee::rpc_params;
+use poise::serenity_prelude::CreateEmbed;
+
+/// Validator alerts sent to you on Discord
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("watch", "unwatch", "watchlist"),
+    subcommand_required,
+    check = "super::alerts_access"
+)]
+pub async fn alerts(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Links a validator to your Discord user and pings you on problems
+///
+/// Example: `/alerts watch name:MyValidator min_reputation:2000 max_heartbeat_lag:300`
+#[poise::command(prefix_command, slash_command)]
+pub async fn watch(
+    ctx: Context<'_>,
+    #[description = "Validator account or vanity name"] name: String,
+    #[description = "Alert when reputation drops below this"] min_reputation: Option<i32>,
+    #[description = "Alert when the last heartbeat is this many blocks behind"]
+    max_heartbeat_lag: Option<u32>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let network = super::network(ctx, network)?;
+    let _permit = super::rpc_permit(ctx).await?;
+    let accounts: AccountList = network
+        .rpc
+        .request("cf_accounts", rpc_params![])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let Some(acc) = search_account_by_name(&accounts, name) else {
+        poise::say_reply(ctx, "Account or vanity name not found").await?;
+        return Ok(());
+    };
+    let account_info: AccountInfo = network
+        .rpc
+        .request("cf_account_info", rpc_params![&acc.0])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    if !matches!(account_info, AccountInfo::Validator { .. }) {
+        let response = format!("Account is not a validator: `{}`", acc.0);
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let channel_id = ctx
+        .data()
+        .settings
+        .get(ctx.guild_id())?
+        .alert_channel
+        .unwrap_or(ctx.channel_id().get());
+    ctx.data().db.upsert_validator_watch(
+        ctx.author().id.get(),
+        channel_id,
+        &acc.0,
+        &network.name,
+        min_reputation,
+        max_heartbeat_lag,
+    )?;
+    let response = format!(
+        "Watching `{}` on {}, alerts go to <#{}>",
+        acc.0, network.name, channel_id
+    );
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Stops alerts for a validator
+///
+/// Example: `/alerts unwatch account:cFK...`
+#[poise::command(prefix_command, slash_command)]
+pub async fn unwatch(
+    ctx: Context<'_>,
+    #[description = "Validator account or vanity name"] name: String,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    let network = super::network(ctx, network)?;
+    let db = &ctx.data().db;
+    let user_id = ctx.author().id.get();
+    let watches = db.validator_watches_for_user(user_id)?;
+    let watched = |account: &str| {
+        watches
+            .iter()
+            .any(|watch| watch.account == account && watch.network == network.name)
+    };
+    // Vanity names are resolved like `/alerts watch`, but only to accounts being watched.
+    let account = match watched(&name) {
+        true => name,
+        false => {
+            let accounts: AccountList = network
+                .rpc
+                .request("cf_accounts", rpc_params![])
+                .await
+                .map_err(|err| format!("Request failed: {err}"))?;
+            search_accounts_by_name(&accounts, &name)
+                .into_iter()
+                .find(|acc| watched(&acc.0))
+                .map_or(name, |acc| acc.0)
+        }
+    };
+    let response = match db.remove_validator_watch(user_id, &account, &network.name)? {
+        true => format!("Stopped watching `{}` on {}", account, network.name),
+        false => format!("Not watching `{}` on {}", account, network.name),
+    };
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Lists the validators you are watching
+///
+/// Example: `/alerts watchlist`
+#[poise::command(prefix_command, slash_command)]
+pub async fn watchlist(ctx: Context<'_>) -> Result<(), Error> {
+    let watches = ctx
+        .data()
+        .db
+        .validator_watches_for_user(ctx.author().id.get())?;
+    if watches.is_empty() {
+        poise::say_reply(ctx, "You are not watching any validators").await?;
+        return Ok(());
+    }
+    let mut embed = CreateEmbed::new()
+        .title("Validator Watchlist")
+        .colour(ctx.data().colours.neutral);
+    for watch in watches {
+        embed = embed.field(
+            watch.account,
+            format!(
+                "Network: {}\nChannel: <#{}>\nMin. reputation: {}\nMax. heartbeat lag: {}",
+                watch.network,
+                watch.channel_id,
+                watch
+                    .min_reputation
+                    .map_or("default".to_string(), |r| r.to_string()),
+                watch
+                    .max_heartbeat_lag
+                    .map_or("default".to_string(), |l| l.to_string()),
+            ),
+            false,
+        );
+    }
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(embed)
+            .ephemeral(super::ephemeral(ctx)?),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,20 +1,21 @@
+use crate::network::Network;
+use crate::rpc::block_at::BlockAt;
 use crate::util::util::{asset_in_amount, bool_to_emoji};
-use jsonrpsee::core::client::ClientT;
 use jsonrpsee::core::Serialize;
 use jsonrpsee::rpc_params;
-use poise::serenity_prelude::{self as serenity, CreateEmbed};
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter};
 use serde::Deserialize;
-use serenity::Colour;
 use std::collections::{BTreeMap, HashMap};
 use tap::pipe::Pipe;
 use web3::types::{Address, H256, U256, U64};
 
+use time::Duration;
 use time::OffsetDateTime as DateTime;
-use time::{format_description, Duration};
+use time_tz::Tz;
 
 use crate::{Context, Error};
 
-const DATE_FORMAT: &str = "[year]-[month]-[day] [hour]:[minute]:[second]";
+pub const DATE_FORMAT: &str = "[year]-[month]-[day] [hour]:[minute]:[second]";
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(bound = "")]
@@ -50,30 +51,11 @@ pub struct SystemHealth {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(bound = "")]
-pub struct AccountList(Vec<AccountPair>);
+pub struct AccountList(pub Vec<AccountPair>);
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(bound = "")]
-struct AccountPair(String, String);
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(bound = "")]
-pub struct AccountInfoV2 {
-    balance: U256,
-    bond: U256,
-    last_heartbeat: u32,
-    reputation_points: u16,
-    keyholder_epochs: Vec<u64>,
-    is_current_authority: bool,
-    is_current_backup: bool,
-    is_qualified: bool,
-    is_online: bool,
-    is_bidding: bool,
-    bound_redeem_address: Option<Address>,
-    apy_bp: Option<u32>,
-    #[serde(skip_deserializing)]
-    restricted_balances: Option<HashMap<Address, U256>>,
-}
+pub struct AccountPair(pub String, pub String);
 
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "role", rename_all = "snake_case")]
@@ -106,6 +88,7 @@ pub enum AccountInfo {
     },
 }
 
+/// Chainflip network, auction and account data
 #[poise::command(
     prefix_command,
     slash_command,
@@ -117,242 +100,289 @@ pub async fn cf(_: Context<'_>) -> Result<(), Error> {
 }
 
 /// Displays Chainflip endpoint status
+///
+/// Example: `/cf status`
 #[poise::command(slash_command, prefix_command)]
-pub async fn status(ctx: Context<'_>) -> Result<(), Error> {
+pub async fn status(
+    ctx: Context<'_>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
     ctx.defer().await?;
-    let version: String = ctx
-        .data()
-        .http_client
-        .request("system_version", rpc_params![])
-        .await
-        .expect("request failed");
-    let health: SystemHealth = ctx
-        .data()
-        .http_client
-        .request("system_health", rpc_params![])
-        .await
-        .expect("request failed");
+    let network = super::network(ctx, network)?;
+    let _permit = super::rpc_permit(ctx).await?;
+    network.rpc.probe_all().await;
     ctx.send(
         poise::CreateReply::default()
-            .embed(
-                CreateEmbed::new()
-                    .title("System Status")
-                    .colour(Colour::DARK_GREY)
-                    .field("Version", version, true)
-                    .field("Peers", format!("{}", health.peers), true)
-                    .field("Synced", format!("{}", !health.isSyncing), true),
-            )
-            .ephemeral(false),
+            .embed(status_embed(network))
+            .ephemeral(super::ephemeral(ctx)?),
     )
     .await?;
     Ok(())
 }
 
+/// Endpoint health from the latest probes.
+pub fn status_embed(network: &Network) -> CreateEmbed {
+    let rpc = &network.rpc;
+    let mut embed = CreateEmbed::new()
+        .title("System Status")
+        .colour(network.colour)
+        .pipe(|it| network.tag(it));
+    for (i, (url, health)) in rpc.health().into_iter().enumerate() {
+        let name = match i {
+            0 => format!("{} (active)", url),
+            _ => url,
+        };
+        let value = match health.error {
+            Some(err) => format!("Unreachable: {}", err),
+            None => format!(
+                "Version: {}\nPeers: {}\nSynced: {}\nLatency: {}",
+                health.version.unwrap_or("-".to_string()),
+                health.peers.map_or("-".to_string(), |p| p.to_string()),
+                bool_to_emoji(!health.is_syncing),
+                health
+                    .latency
+                    .map_or("-".to_string(), |l| format!("{} ms", l.as_millis())),
+            ),
+        };
+        embed = embed.field(name, value, false);
+    }
+    let stats = rpc.cache_stats();
+    embed = embed.field(
+        "Cache",
+        format!("Hits: {}\nMisses: {}", stats.hits, stats.misses),
+        false,
+    );
+    embed
+}
+
 /// Displays auction related data
+///
+/// Examples: `/cf auction`, `/cf auction at_block:5000000`
 #[poise::command(slash_command, prefix_command)]
-pub async fn auction(ctx: Context<'_>) -> Result<(), Error> {
+pub async fn auction(
+    ctx: Context<'_>,
+    #[description = "Query at this block number instead of the latest"] at_block: Option<u32>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
     ctx.defer().await?;
-    let date_format = format_description::parse(DATE_FORMAT)?;
-    let auction: AuctionState = ctx
-        .data()
-        .http_client
-        .request("cf_auction_state", rpc_params![])
+    let network = super::network(ctx, network)?;
+    let at = BlockAt::resolve(&network.rpc, at_block).await?;
+    // Historical state doesn't change, so only the latest gets a Refresh button.
+    super::interactive(ctx, 0, at.is_latest(), move || async move {
+        let timezone = ctx.data().settings.get(ctx.guild_id())?.timezone();
+        Ok(vec![vec![auction_embed(network, &at, timezone).await?]])
+    })
+    .await
+}
+
+/// Auction state with the next rotation shown in `timezone`.
+pub async fn auction_embed(
+    network: &Network,
+    at: &BlockAt,
+    timezone: &Tz,
+) -> Result<CreateEmbed, Error> {
+    let rpc = &network.rpc;
+    let auction: AuctionState = rpc
+        .request("cf_auction_state", rpc_params![at.hash])
         .await
-        .expect("request failed");
-    let block_header: BlockHeader = ctx
-        .data()
-        .http_client
-        .request("chain_getHeader", rpc_params![])
+        .map_err(|err| at.request_error(err))?;
+    let block_header: BlockHeader = rpc
+        .request("chain_getHeader", rpc_params![at.hash])
         .await
-        .expect("request failed");
-    let current_epoch_at: u32 = ctx
-        .data()
-        .http_client
-        .request("cf_current_epoch_started_at", rpc_params![])
+        .map_err(|err| at.request_error(err))?;
+    let current_epoch_at: u32 = rpc
+        .request("cf_current_epoch_started_at", rpc_params![at.hash])
         .await
-        .expect("request failed");
-    let current_epoch: u32 = ctx
-        .data()
-        .http_client
-        .request("cf_current_epoch", rpc_params![])
+        .map_err(|err| at.request_error(err))?;
+    let current_epoch: u32 = rpc
+        .request("cf_current_epoch", rpc_params![at.hash])
         .await
-        .expect("request failed");
-    let seconds_to_rotation: u32 =
-        (auction.blocks_per_epoch - (block_header.number.as_u32() - current_epoch_at)) * 6;
-    let now = DateTime::now_utc();
-    ctx.send(
-        poise::CreateReply::default()
-            .embed(
-                CreateEmbed::new()
-                    .title("Auction State")
-                    .colour(Colour::DARK_GREY)
-                    .field(
-                        "Min. Active Bid",
-                        format!(
-                            "{}",
-                            asset_in_amount(&auction.min_active_bid, "FLIP").round_dp(3)
-                        ),
-                        true,
-                    )
-                    .field("Current block", format!("{}", block_header.number), true)
-                    .field("Current epoch", format!("{}", current_epoch), true)
-                    .field(
-                        "Next rotation",
-                        format!(
-                            "{} UTC",
-                            (now + Duration::seconds(seconds_to_rotation as i64))
-                                .format(&date_format)
-                                .unwrap()
-                        ),
-                        true,
-                    ),
-            )
-            .ephemeral(false),
-    )
-    .await?;
-    Ok(())
+        .map_err(|err| at.request_error(err))?;
+    let blocks_to_rotation = auction.blocks_per_epoch.saturating_sub(
+        block_header
+            .number
+            .as_u32()
+            .saturating_sub(current_epoch_at),
+    );
+    let next_rotation = super::format_time(
+        DateTime::now_utc() + Duration::seconds(blocks_to_rotation as i64 * 6),
+        timezone,
+    )?;
+    Ok(CreateEmbed::new()
+        .title("Auction State")
+        .colour(network.colour)
+        .pipe(|it| network.tag(it))
+        .field(
+            "Min. Active Bid",
+            format!(
+                "{}",
+                asset_in_amount(&auction.min_active_bid, "FLIP").round_dp(3)
+            ),
+            true,
+        )
+        .field("Current block", format!("{}", block_header.number), true)
+        .field("Current epoch", format!("{}", current_epoch), true)
+        .pipe(|it| match at.is_latest() {
+            true => it.field("Next rotation", next_rotation, true),
+            false => it.field(
+                "Blocks to rotation",
+                format!("{}", blocks_to_rotation),
+                true,
+            ),
+        }))
 }
 
+/// Shows an account's role, balances and validator state
+///
+/// Examples: `/cf account_info name:cFK...`, `/cf account_info name:MyValidator`
 #[poise::command(slash_command, prefix_command)]
 pub async fn account_info(
     ctx: Context<'_>,
     #[description = "Account name or address"] name: String,
+    #[description = "Query at this block number instead of the latest"] at_block: Option<u32>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
 ) -> Result<(), Error> {
-    let accounts: AccountList = ctx
-        .data()
-        .http_client
-        .request("cf_accounts", rpc_params![])
-        .await
-        .expect("request failed");
-    match search_account_by_name(&accounts, name) {
-        Some(acc) => {
-            let account_info: AccountInfo = ctx
-                .data()
-                .http_client
-                .request("cf_account_info", rpc_params![&acc.0])
-                .await
-                .expect("request failed");
-            match account_info {
-                AccountInfo::LiquidityProvider {
-                    balances,
-                    flip_balance,
-                    ..
-                } => {
-                    ctx.send(
-                        poise::CreateReply::default()
-                            .embed(
-                                CreateEmbed::new()
-                                    .title("Liquidity Provider")
-                                    .colour(Colour::GOLD)
-                                    .field("Account", format!("{}", &acc.0), false)
-                                    //.field("Vanity Name", format!("{}", &acc.1), true)
-                                    .field(
-                                        "Liquidity Balances",
-                                        balance_map_format(&balances),
-                                        true,
-                                    )
-                                    .field(
-                                        "Account Balance (FLIP)",
-                                        format!(
-                                            "{}",
-                                            asset_in_amount(&flip_balance, "FLIP").round_dp(4)
-                                        ),
-                                        true,
-                                    ),
-                            )
-                            .ephemeral(false),
-                    )
-                    .await?;
-                }
-                AccountInfo::Validator {
-                    flip_balance,
-                    reputation_points,
-                    bound_redeem_address,
-                    is_online,
-                    is_bidding,
-                    is_current_authority,
-                    is_qualified,
-                    is_current_backup,
-                    ..
-                } => {
-                    ctx.send(
-                        poise::CreateReply::default()
-                            .embed(
-                                CreateEmbed::new()
-                                    .title("Validator")
-                                    .colour(Colour::DARK_GREY)
-                                    .field("Account", format!("{}", &acc.0), false)
-                                    .field("Vanity Name", format!("{}", &acc.1), true)
-                                    .field(
-                                        "Balance",
-                                        format!(
-                                            "{}",
-                                            asset_in_amount(&flip_balance, "FLIP").round_dp(4)
-                                        ),
-                                        true,
-                                    )
-                                    .field("Reputation", format!("{}", &reputation_points), true)
-                                    .pipe(|it| {
-                                        if bound_redeem_address.is_some() {
-                                            it.field(
-                                                "Bound Redeem Address",
-                                                format!("{}", &bound_redeem_address.unwrap()),
-                                                true,
-                                            )
-                                        } else {
-                                            it
-                                        }
-                                    })
-                                    .field("Online", format!("{}", bool_to_emoji(is_online)), true)
-                                    .field(
-                                        "Bidding",
-                                        format!("{}", bool_to_emoji(is_bidding)),
-                                        true,
-                                    )
-                                    .field(
-                                        "Authority",
-                                        format!("{}", bool_to_emoji(is_current_authority)),
-                                        true,
-                                    )
-                                    .field(
-                                        "Qualified",
-                                        format!("{}", bool_to_emoji(is_qualified)),
-                                        true,
-                                    )
-                                    .field(
-                                        "Backup",
-                                        format!("{}", bool_to_emoji(is_current_backup)),
-                                        true,
-                                    ),
-                            )
-                            .ephemeral(false),
-                    )
-                    .await?;
-                }
-                _ => {}
-            }
+    ctx.defer().await?;
+    let network = super::network(ctx, network)?;
+    let (at, matches) = {
+        let _permit = super::rpc_permit(ctx).await?;
+        let at = BlockAt::resolve(&network.rpc, at_block).await?;
+        // Resolved at the same block, so accounts that existed then are found.
+        let accounts: AccountList = network
+            .rpc
+            .request("cf_accounts", rpc_params![at.hash])
+            .await
+            .map_err(|err| at.request_error(err))?;
+        (at, search_accounts_by_name(&accounts, &name))
+    };
+    if matches.is_empty() {
+        poise::say_reply(ctx, "Account or vanity name not found").await?;
+        return Ok(());
+    }
+    let matches = &matches[..matches.len().min(MAX_ACCOUNT_PAGES)];
+    // The permit is taken per render so it isn't held while the buttons wait for presses.
+    super::interactive(ctx, 0, at.is_latest(), move || async move {
+        let _permit = super::rpc_permit(ctx).await?;
+        let mut pages = Vec::new();
+        for acc in matches {
+            pages.push(vec![account_embed(network, &at, acc).await?]);
         }
-        None => {
-            poise::say_reply(ctx, "Account or vanity name not found").await?;
+        Ok(pages)
+    })
+    .await
+}
+
+/// Accounts matching a name are paged through, up to this many.
+const MAX_ACCOUNT_PAGES: usize = 10;
+
+async fn account_embed(
+    network: &Network,
+    at: &BlockAt,
+    acc: &AccountPair,
+) -> Result<CreateEmbed, Error> {
+    let account_info: AccountInfo = network
+        .rpc
+        .request("cf_account_info", rpc_params![&acc.0, at.hash])
+        .await
+        .map_err(|err| at.request_error(err))?;
+    let embed = match account_info {
+        AccountInfo::LiquidityProvider {
+            balances,
+            flip_balance,
+            ..
+        } => CreateEmbed::new()
+            .title("Liquidity Provider")
+            .colour(network.colour)
+            .pipe(|it| network.tag(it))
+            .pipe(|it| at_block_footer(it, at))
+            .field("Account", acc.0.clone(), false)
+            //.field("Vanity Name", acc.1.clone(), true)
+            .field("Liquidity Balances", balance_map_format(&balances), true)
+            .field(
+                "Account Balance (FLIP)",
+                format!("{}", asset_in_amount(&flip_balance, "FLIP").round_dp(4)),
+                true,
+            ),
+        AccountInfo::Validator {
+            flip_balance,
+            reputation_points,
+            bound_redeem_address,
+            is_online,
+            is_bidding,
+            is_current_authority,
+            is_qualified,
+            is_current_backup,
+            ..
+        } => CreateEmbed::new()
+            .title("Validator")
+            .colour(network.colour)
+            .pipe(|it| network.tag(it))
+            .pipe(|it| at_block_footer(it, at))
+            .field("Account", acc.0.clone(), false)
+            .field("Vanity Name", acc.1.clone(), true)
+            .field(
+                "Balance",
+                format!("{}", asset_in_amount(&flip_balance, "FLIP").round_dp(4)),
+                true,
+            )
+            .field("Reputation", format!("{}", &reputation_points), true)
+            .pipe(|it| match bound_redeem_address {
+                Some(address) => it.field("Bound Redeem Address", format!("{}", address), true),
+                None => it,
+            })
+            .field("Online", bool_to_emoji(is_online), true)
+            .field("Bidding", bool_to_emoji(is_bidding), true)
+            .field("Authority", bool_to_emoji(is_current_authority), true)
+            .field("Qualified", bool_to_emoji(is_qualified), true)
+            .field("Backup", bool_to_emoji(is_current_backup), true),
+        AccountInfo::Unregistered { flip_balance } | AccountInfo::Broker { flip_balance } => {
+            CreateEmbed::new()
+                .title("Account")
+                .colour(network.colour)
+                .pipe(|it| network.tag(it))
+                .pipe(|it| at_block_footer(it, at))
+                .field("Account", acc.0.clone(), false)
+                .field(
+                    "Balance",
+                    format!("{}", asset_in_amount(&flip_balance, "FLIP").round_dp(4)),
+                    true,
+                )
         }
     };
-    Ok(())
+    Ok(embed)
+}
+
+pub fn search_account_by_name(accs: &AccountList, name: String) -> Option<AccountPair> {
+    search_accounts_by_name(accs, &name).into_iter().next()
+}
+
+/// Accounts whose address or vanity name contains `name`, the last listed first.
+pub fn search_accounts_by_name(accs: &AccountList, name: &str) -> Vec<AccountPair> {
+    accs.0
+        .iter()
+        .rev()
+        .filter(|x| x.0.contains(name) || x.1.contains(name))
+        .cloned()
+        .collect()
 }
 
-fn search_account_by_name(accs: &AccountList, name: String) -> Option<AccountPair> {
-    let mut accounts = accs.clone();
-    accounts
-        .0
-        .retain(|x| x.0.contains(name.as_str()) || x.1.contains(name.as_str()));
-    match accounts.0.len() {
-        len if len > 0 => Some(accounts.0.pop().unwrap()),
-        _ => None,
+/// Notes the block in the footer of embeds built from historical state.
+pub fn at_block_footer(embed: CreateEmbed, at: &BlockAt) -> CreateEmbed {
+    match at.number {
+        Some(number) => embed.footer(CreateEmbedFooter::new(format!("At block {}", number))),
+        None => embed,
     }
 }
 
 fn balance_map_format(balances: &HashMap<String, HashMap<String, U256>>) -> String {
     let mut balances_formatted = String::from("");
-    for (key, val) in &*balances {
+    for (key, val) in balances {
         balances_formatted.push_str(format!("{}\n", key).as_str());
         for (ikey, ival) in val {
             balances_formatted.push_str(
@@ -0,0 +1,333 @@
+use crate::config::valid_prefix;
+use crate::{Context, Error};
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
+use time_tz::{timezones, TimeZone};
+
+/// Server settings, for admins
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands(
+        "show",
+        "network",
+        "replies",
+        "channel_add",
+        "channel_remove",
+        "alert_channel",
+        "timezone",
+        "role",
+        "prefix"
+    ),
+    subcommand_required,
+    guild_only,
+    check = "super::config_access"
+)]
+pub async fn config(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows this server's settings
+#[poise::command(prefix_command, slash_command)]
+pub async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let settings = ctx.data().settings.get(ctx.guild_id())?;
+    let or_default = |value: Option<String>, default: &str| {
+        value.unwrap_or_else(|| format!("{} (default)", default))
+    };
+    let channels = match settings.allowed_channels.is_empty() {
+        true => "Any".to_string(),
+        false => settings
+            .allowed_channels
+            .iter()
+            .map(|id| format!("<#{}>", id))
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    let embed = CreateEmbed::new()
+        .title("Server Settings")
+        .colour(ctx.data().colours.neutral)
+        .field(
+            "Network",
+            or_default(
+                settings.network.clone(),
+                &ctx.data().networks.default().name,
+            ),
+            true,
+        )
+        .field(
+            "Replies",
+            match settings.ephemeral {
+                true => "Private",
+                false => "Public",
+            },
+            true,
+        )
+        .field("Allowed channels", channels, false)
+        .field(
+            "Alert channel",
+            settings
+                .alert_channel
+                .map_or("Where /alerts watch is used".to_string(), |id| {
+                    format!("<#{}>", id)
+                }),
+            true,
+        )
+        .field("Timezone", settings.timezone().name(), true)
+        .field(
+            "Prefix",
+            or_default(settings.prefix.clone(), &ctx.data().default_prefix),
+            true,
+        )
+        .field(
+            "Roles",
+            RoleGroup::all()
+                .iter()
+                .map(|group| {
+                    let role = settings
+                        .roles
+                        .get(group.key())
+                        .map_or(group.unset().to_string(), |id| format!("<@&{}>", id));
+                    format!("`/{}`: {}", group.key(), role)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            false,
+        );
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Sets the network this server's commands use by default
+#[poise::command(prefix_command, slash_command)]
+pub async fn network(
+    ctx: Context<'_>,
+    #[description = "Network to use by default, unset to use the bot's default"]
+    #[autocomplete = "super::autocomplete_network"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let networks = &ctx.data().networks;
+    if let Some(name) = name.as_ref().filter(|name| networks.get(name).is_none()) {
+        let response = format!(
+            "Unknown network: `{}`, expected one of {}",
+            name,
+            networks.names().join(", ")
+        );
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let response = match &name {
+        Some(name) => format!("Default network set to `{}`", name),
+        None => format!("Default network reset to `{}`", networks.default().name),
+    };
+    ctx.data()
+        .settings
+        .update(guild_id, |settings| settings.network = name)?;
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy, Debug)]
+pub enum Replies {
+    Public,
+    Private,
+}
+
+/// Sets whether command replies are visible to everyone or only the caller
+#[poise::command(prefix_command, slash_command)]
+pub async fn replies(
+    ctx: Context<'_>,
+    #[description = "Reply visibility"] visibility: Replies,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    ctx.data().settings.update(guild_id, |settings| {
+        settings.ephemeral = matches!(visibility, Replies::Private)
+    })?;
+    let response = format!("Replies are now {:?}", visibility).to_lowercase();
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Allows commands in a channel, limiting them to the allowed channels
+#[poise::command(prefix_command, slash_command, rename = "channel-add")]
+pub async fn channel_add(
+    ctx: Context<'_>,
+    #[description = "Channel to allow"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    ctx.data().settings.update(guild_id, |settings| {
+        if !settings.allowed_channels.contains(&channel.id.get()) {
+            settings.allowed_channels.push(channel.id.get());
+        }
+    })?;
+    poise::say_reply(ctx, format!("Commands are allowed in <#{}>", channel.id)).await?;
+    Ok(())
+}
+
+/// Removes a channel from the allowed channels, allowing all once none are left
+#[poise::command(prefix_command, slash_command, rename = "channel-remove")]
+pub async fn channel_remove(
+    ctx: Context<'_>,
+    #[description = "Channel to remove"]
+    #[channel_types("Text")]
+    channel: serenity::GuildChannel,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let settings = ctx.data().settings.update(guild_id, |settings| {
+        settings
+            .allowed_channels
+            .retain(|id| *id != channel.id.get())
+    })?;
+    let response = match settings.allowed_channels.is_empty() {
+        true => "Commands are allowed in every channel".to_string(),
+        false => format!("Commands are no longer allowed in <#{}>", channel.id),
+    };
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Sets the channel validator alerts from this server are posted to
+#[poise::command(prefix_command, slash_command, rename = "alert-channel")]
+pub async fn alert_channel(
+    ctx: Context<'_>,
+    #[description = "Channel for alerts, unset to post where /alerts watch is used"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let channel = channel.map(|channel| channel.id.get());
+    ctx.data()
+        .settings
+        .update(guild_id, |settings| settings.alert_channel = channel)?;
+    let response = match channel {
+        Some(id) => format!("New alerts will be posted to <#{}>", id),
+        None => "New alerts will be posted where /alerts watch is used".to_string(),
+    };
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+async fn autocomplete_timezone(_: Context<'_>, partial: &str) -> Vec<String> {
+    let partial = partial.to_lowercase();
+    timezones::iter()
+        .map(|tz| tz.name())
+        .filter(|name| name.to_lowercase().contains(&partial))
+        .take(25)
+        .map(String::from)
+        .collect()
+}
+
+/// Sets the timezone dates are shown in
+#[poise::command(prefix_command, slash_command)]
+pub async fn timezone(
+    ctx: Context<'_>,
+    #[description = "IANA timezone, e.g. Europe/Berlin, unset for UTC"]
+    #[autocomplete = "autocomplete_timezone"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    if let Some(name) = name
+        .as_ref()
+        .filter(|name| timezones::get_by_name(name).is_none())
+    {
+        poise::say_reply(ctx, format!("Unknown timezone: `{}`", name)).await?;
+        return Ok(());
+    }
+    let settings = ctx
+        .data()
+        .settings
+        .update(guild_id, |settings| settings.timezone = name)?;
+    let response = format!("Dates are shown in {}", settings.timezone().name());
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Sets the prefix for prefix commands, mentioning the bot always works too
+#[poise::command(prefix_command, slash_command)]
+pub async fn prefix(
+    ctx: Context<'_>,
+    #[description = "Prefix, e.g. ?, unset to use the bot's default"] prefix: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    if let Some(prefix) = prefix.as_ref().filter(|prefix| !valid_prefix(prefix)) {
+        let response = format!(
+            "Invalid prefix: `{}`, expected 1 to 5 characters without spaces",
+            prefix
+        );
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let settings = ctx
+        .data()
+        .settings
+        .update(guild_id, |settings| settings.prefix = prefix)?;
+    let prefix = settings.prefix.unwrap_or(ctx.data().default_prefix.clone());
+    poise::say_reply(ctx, format!("Prefix commands now start with `{}`", prefix)).await?;
+    Ok(())
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy, Debug)]
+pub enum RoleGroup {
+    Config,
+    Alerts,
+    Dashboard,
+    Digest,
+}
+
+impl RoleGroup {
+    fn all() -> [RoleGroup; 4] {
+        [
+            RoleGroup::Config,
+            RoleGroup::Alerts,
+            RoleGroup::Dashboard,
+            RoleGroup::Digest,
+        ]
+    }
+
+    /// Name of the command group, as stored in the settings.
+    fn key(self) -> &'static str {
+        match self {
+            RoleGroup::Config => "config",
+            RoleGroup::Alerts => "alerts",
+            RoleGroup::Dashboard => "dashboard",
+            RoleGroup::Digest => "digest",
+        }
+    }
+
+    /// Who may use the group without a role.
+    fn unset(self) -> &'static str {
+        match self {
+            RoleGroup::Config | RoleGroup::Dashboard | RoleGroup::Digest => "Manage Server only",
+            RoleGroup::Alerts => "Everyone",
+        }
+    }
+}
+
+/// Sets the role required to use a command group, Manage Server always has access
+#[poise::command(prefix_command, slash_command)]
+pub async fn role(
+    ctx: Context<'_>,
+    #[description = "Command group"] group: RoleGroup,
+    #[description = "Required role, unset to use the default access"] role: Option<serenity::Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let role = role.map(|role| role.id.get());
+    ctx.data()
+        .settings
+        .update(guild_id, |settings| match role {
+            Some(role) => {
+                settings.roles.insert(group.key().to_string(), role);
+            }
+            None => {
+                settings.roles.remove(group.key());
+            }
+        })?;
+    let response = match role {
+        Some(id) => format!("`/{}` now requires <@&{}>", group.key(), id),
+        None => format!("`/{}` is now available to: {}", group.key(), group.unset()),
+    };
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
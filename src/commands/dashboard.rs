@@ -0,0 +1,221 @@
+use super::{cf, lp};
+use crate::db::dashboards::Dashboard;
+use crate::network::Network;
+use crate::rpc::block_at::BlockAt;
+use crate::{Context, Error};
+use poise::serenity_prelude::{
+    ChannelId, CreateEmbed, CreateEmbedFooter, CreateMessage, MessageId, Timestamp,
+};
+use time_tz::Tz;
+
+/// Dashboards a server may have, each is edited every few blocks.
+const MAX_DASHBOARDS: usize = 10;
+
+#[derive(poise::ChoiceParameter, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DashboardKind {
+    #[name = "Network status"]
+    Status,
+    #[name = "Auction countdown"]
+    Auction,
+    #[name = "Pool top of book"]
+    Book,
+}
+
+impl DashboardKind {
+    /// Name of the kind, as stored in the database.
+    pub fn key(self) -> &'static str {
+        match self {
+            DashboardKind::Status => "status",
+            DashboardKind::Auction => "auction",
+            DashboardKind::Book => "book",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<DashboardKind> {
+        [
+            DashboardKind::Status,
+            DashboardKind::Auction,
+            DashboardKind::Book,
+        ]
+        .into_iter()
+        .find(|kind| kind.key() == key)
+    }
+}
+
+/// Builds the embed of a dashboard from the latest state.
+pub async fn render(
+    dashboard: &Dashboard,
+    network: &Network,
+    timezone: &Tz,
+) -> Result<CreateEmbed, Error> {
+    let kind = DashboardKind::from_key(&dashboard.kind)
+        .ok_or(format!("unknown dashboard kind: {}", dashboard.kind))?;
+    let embed = match kind {
+        DashboardKind::Status => cf::status_embed(network),
+        DashboardKind::Auction => cf::auction_embed(network, &BlockAt::default(), timezone).await?,
+        DashboardKind::Book => {
+            let base = dashboard
+                .base
+                .as_deref()
+                .ok_or("pool dashboard without a base")?;
+            let quote = dashboard
+                .quote
+                .as_deref()
+                .ok_or("pool dashboard without a quote")?;
+            lp::book_embed(network, base, quote).await?
+        }
+    };
+    Ok(embed
+        .footer(CreateEmbedFooter::new("Last updated"))
+        .timestamp(Timestamp::now()))
+}
+
+/// Messages that keep themselves up to date
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("create", "list", "remove"),
+    subcommand_required,
+    guild_only,
+    check = "super::dashboard_access"
+)]
+pub async fn dashboard(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Posts a dashboard in this channel that is updated every few blocks
+///
+/// Examples: `/dashboard create kind:Network status`, `/dashboard create kind:Pool top of book base:BTC`
+#[poise::command(prefix_command, slash_command)]
+pub async fn create(
+    ctx: Context<'_>,
+    #[description = "What the dashboard shows"] kind: DashboardKind,
+    #[description = "Base asset, for pool dashboards"] base: Option<String>,
+    #[description = "Quote asset, for pool dashboards"] quote: Option<String>,
+    #[description = "Pin the message, defaults to yes"] pin: Option<bool>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    ctx.defer_ephemeral().await?;
+    let network = super::network(ctx, network)?;
+    let (base, quote) = match kind {
+        DashboardKind::Book => {
+            let Some(base) = base.map(|base| base.to_uppercase()) else {
+                poise::say_reply(ctx, "Pool dashboards need a base asset").await?;
+                return Ok(());
+            };
+            let quote = super::quote(quote);
+            if !network.supports(&base) || !network.supports(&quote) {
+                let response = format!("Pool not supported: `{}-{}`", base, quote);
+                poise::say_reply(ctx, response).await?;
+                return Ok(());
+            }
+            (Some(base), Some(quote))
+        }
+        _ => (None, None),
+    };
+    let db = &ctx.data().db;
+    if db.guild_dashboards(guild_id.get())?.len() >= MAX_DASHBOARDS {
+        let response = format!(
+            "This server already has {} dashboards, remove one with /dashboard remove first",
+            MAX_DASHBOARDS
+        );
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+
+    let mut dashboard = Dashboard {
+        id: 0,
+        guild_id: guild_id.get(),
+        channel_id: ctx.channel_id().get(),
+        message_id: 0,
+        kind: kind.key().to_string(),
+        network: network.name.clone(),
+        base,
+        quote,
+    };
+    let timezone = ctx.data().settings.get(Some(guild_id))?.timezone();
+    let embed = render(&dashboard, network, timezone).await?;
+    let message = ctx
+        .channel_id()
+        .send_message(ctx, CreateMessage::new().embed(embed))
+        .await?;
+    dashboard.message_id = message.id.get();
+    db.insert_dashboard(&dashboard)?;
+
+    let mut response = format!("Dashboard posted: {}", message.link());
+    if pin.unwrap_or(true) {
+        if let Err(err) = message.pin(ctx).await {
+            response += &format!("\nCouldn't pin it: {err}");
+        }
+    }
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Lists this server's dashboards
+#[poise::command(prefix_command, slash_command)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let dashboards = ctx.data().db.guild_dashboards(guild_id.get())?;
+    let lines = dashboards
+        .iter()
+        .map(|dashboard| {
+            let pool = match (&dashboard.base, &dashboard.quote) {
+                (Some(base), Some(quote)) => format!(" {}-{}", base, quote),
+                _ => String::new(),
+            };
+            format!(
+                "`{}`{} on {}: https://discord.com/channels/{}/{}/{}",
+                dashboard.kind,
+                pool,
+                dashboard.network,
+                dashboard.guild_id,
+                dashboard.channel_id,
+                dashboard.message_id
+            )
+        })
+        .collect::<Vec<_>>();
+    let embed = CreateEmbed::new()
+        .title("Dashboards")
+        .colour(ctx.data().colours.neutral)
+        .description(match lines.is_empty() {
+            true => "None yet, post one with /dashboard create".to_string(),
+            false => lines.join("\n"),
+        });
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Stops updating a dashboard and deletes its message
+#[poise::command(prefix_command, slash_command)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Link or ID of the dashboard message"] message: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let message_id = message
+        .trim()
+        .rsplit('/')
+        .next()
+        .and_then(|id| id.parse::<u64>().ok());
+    let db = &ctx.data().db;
+    let dashboard = db
+        .guild_dashboards(guild_id.get())?
+        .into_iter()
+        .find(|dashboard| Some(dashboard.message_id) == message_id);
+    let Some(dashboard) = dashboard else {
+        poise::say_reply(ctx, "No dashboard with that message in this server").await?;
+        return Ok(());
+    };
+    db.remove_dashboard(guild_id.get(), dashboard.message_id)?;
+    // The message may already be gone, which is fine since it's no longer updated either way.
+    let _ = ChannelId::new(dashboard.channel_id)
+        .delete_message(ctx, MessageId::new(dashboard.message_id))
+        .await;
+    poise::say_reply(ctx, "Dashboard removed").await?;
+    Ok(())
+}
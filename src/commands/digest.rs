@@ -0,0 +1,505 @@
+use super::cf::{self, AccountList, AuctionState};
+use super::lp::{PoolOrders, PoolPrice};
+use crate::analytics::concentration::{self, LpShare};
+use crate::analytics::{book, volume::Fills};
+use crate::db::digests::Digest;
+use crate::db::snapshots::AuctionSummary;
+use crate::db::Db;
+use crate::network::Network;
+use crate::schedule::Schedule;
+use crate::tasks::recorder::QUOTE;
+use crate::util::util::{asset_in_amount, shorten_address};
+use crate::{Context, Error};
+use jsonrpsee::rpc_params;
+use poise::serenity_prelude::{self as serenity, CreateEmbed, CreateEmbedFooter};
+use poise::ChoiceParameter;
+use rust_decimal::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use tap::pipe::Pipe;
+use time::OffsetDateTime as DateTime;
+use time_tz::{TimeZone, Tz};
+
+/// Digests a server may have.
+const MAX_DIGESTS: usize = 10;
+
+/// Top LPs listed per pool.
+const TOP_LPS: usize = 3;
+
+/// Validators listed as joined or left, the rest are only counted.
+const MAX_LISTED_VALIDATORS: usize = 10;
+
+#[derive(poise::ChoiceParameter, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Period {
+    Daily,
+    Weekly,
+}
+
+impl Period {
+    /// Name of the period, as stored in the database.
+    pub fn key(self) -> &'static str {
+        match self {
+            Period::Daily => "daily",
+            Period::Weekly => "weekly",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Period> {
+        [Period::Daily, Period::Weekly]
+            .into_iter()
+            .find(|period| period.key() == key)
+    }
+
+    pub fn seconds(self) -> i64 {
+        match self {
+            Period::Daily => 86400,
+            Period::Weekly => 7 * 86400,
+        }
+    }
+
+    /// 9:00 every day, or on Mondays.
+    fn default_schedule(self) -> &'static str {
+        match self {
+            Period::Daily => "0 9 * * *",
+            Period::Weekly => "0 9 * * 1",
+        }
+    }
+
+    fn window(self) -> &'static str {
+        match self {
+            Period::Daily => "24h",
+            Period::Weekly => "7d",
+        }
+    }
+}
+
+struct PoolReport {
+    base: String,
+    /// Current mid price.
+    price: f64,
+    /// Open, high, low and close over the period, from recorded snapshots.
+    history: Option<(f64, f64, f64, f64)>,
+    fills: Option<Fills>,
+    top_lps: Vec<LpShare>,
+}
+
+/// Everything a digest shows, gathered once and rendered for each channel it's posted in.
+pub struct Report {
+    period: Period,
+    at: DateTime,
+    recorded: bool,
+    pools: Vec<PoolReport>,
+    epoch: u32,
+    /// In FLIP.
+    min_active_bid: f64,
+    auction: Option<AuctionSummary>,
+    /// `None` when the node couldn't list them.
+    pub authorities: Option<BTreeSet<String>>,
+    vanity_names: HashMap<String, String>,
+}
+
+/// Collects the report from the same RPC calls and recordings the `/cf` and `/lp` commands use.
+pub async fn gather(network: &Network, db: &Db, period: Period) -> Result<Report, Error> {
+    let rpc = &network.rpc;
+    let at = DateTime::now_utc();
+    let from = at.unix_timestamp() - period.seconds();
+
+    let mut pools = Vec::new();
+    for base in network.assets.iter().filter(|asset| *asset != QUOTE) {
+        let orders: PoolOrders = rpc
+            .request("cf_pool_orders", rpc_params![base, QUOTE])
+            .await?;
+        let price: PoolPrice = rpc
+            .request("cf_pool_price", rpc_params![base, QUOTE])
+            .await?;
+        let (history, fills) = match network.recorded {
+            true => {
+                let snapshots = db.pool_snapshots(base, QUOTE, from, at.unix_timestamp() + 1)?;
+                let history = match (snapshots.first(), snapshots.last()) {
+                    (Some(first), Some(last)) => Some((
+                        first.open,
+                        snapshots.iter().map(|s| s.high).fold(f64::MIN, f64::max),
+                        snapshots.iter().map(|s| s.low).fold(f64::MAX, f64::min),
+                        last.close,
+                    )),
+                    _ => None,
+                };
+                let fills = db.pool_fills_since(base, QUOTE, from)?;
+                (history, Some(fills))
+            }
+            false => (None, None),
+        };
+        let mut top_lps = concentration::analyze(&orders, base, QUOTE, price.tick).shares;
+        top_lps.truncate(TOP_LPS);
+        pools.push(PoolReport {
+            base: base.clone(),
+            price: book::mid_price(&orders, base, QUOTE, price.tick),
+            history,
+            fills,
+            top_lps,
+        });
+    }
+
+    let epoch: u32 = rpc.request("cf_current_epoch", rpc_params![]).await?;
+    let auction_state: AuctionState = rpc.request("cf_auction_state", rpc_params![]).await?;
+    let min_active_bid = asset_in_amount(&auction_state.min_active_bid, "FLIP")
+        .to_f64()
+        .unwrap_or_default();
+    let auction = match network.recorded {
+        true => db.auction_summary_since(from)?,
+        false => None,
+    };
+
+    // The rest of the report is still worth posting without the authority set.
+    let authorities = match rpc
+        .request::<Vec<String>>("cf_current_authorities", rpc_params![])
+        .await
+    {
+        Ok(authorities) => Some(authorities.into_iter().collect()),
+        Err(err) => {
+            eprintln!("digest: {} authorities: {err}", network.name);
+            None
+        }
+    };
+    let accounts: AccountList = rpc.request("cf_accounts", rpc_params![]).await?;
+    let vanity_names = accounts
+        .0
+        .into_iter()
+        .filter(|account| !account.1.is_empty())
+        .map(|account| (account.0, account.1))
+        .collect();
+
+    Ok(Report {
+        period,
+        at,
+        recorded: network.recorded,
+        pools,
+        epoch,
+        min_active_bid,
+        auction,
+        authorities,
+        vanity_names,
+    })
+}
+
+/// Builds the embeds of a report, listing validators that joined or left since `previous`.
+pub fn render(
+    report: &Report,
+    network: &Network,
+    previous: Option<&BTreeSet<String>>,
+    timezone: &Tz,
+) -> Result<Vec<CreateEmbed>, Error> {
+    let title = |section: &str| format!("{} Digest · {}", report.period.name(), section);
+    let embed = |section: &str| {
+        CreateEmbed::new()
+            .title(title(section))
+            .colour(network.colour)
+            .pipe(|it| network.tag(it))
+    };
+    let window = report.period.window();
+
+    let pools = report.pools.iter().fold(
+        embed("Pools").description(format!(
+            "The last {} up to {}",
+            window,
+            super::format_time(report.at, timezone)?
+        )),
+        |embed, pool| {
+            let mut value = match pool.history {
+                Some((open, high, low, close)) => format!(
+                    "Price: {:.4} ({})\nRange: {:.4} - {:.4}",
+                    close,
+                    percent_change(open, close),
+                    low,
+                    high
+                ),
+                None => format!("Price: {:.4}", pool.price),
+            };
+            if let Some(fills) = &pool.fills {
+                value += &format!(
+                    "\nVolume: {:.2} {}\nFees: {:.2} {}",
+                    fills.volume,
+                    QUOTE,
+                    fills.fees(),
+                    QUOTE
+                );
+            }
+            embed.field(format!("{}-{}", pool.base, QUOTE), value, true)
+        },
+    );
+    let pools = match report.recorded {
+        true => pools.footer(CreateEmbedFooter::new(
+            "Volume and fees are estimated from order book changes",
+        )),
+        false => pools.footer(CreateEmbedFooter::new(
+            "History isn't recorded for this network, so only current prices are shown",
+        )),
+    };
+
+    let top_lps = report.pools.iter().fold(embed("Top LPs"), |embed, pool| {
+        let lines = pool
+            .top_lps
+            .iter()
+            .enumerate()
+            .map(|(rank, share)| {
+                format!(
+                    "{}. `{}` ±1% {:.1}% | ToB {:.1}%",
+                    rank + 1,
+                    shorten_address(&share.lp),
+                    share.depth_1pct * 100.0,
+                    share.top_of_book * 100.0
+                )
+            })
+            .collect::<Vec<_>>();
+        embed.field(
+            format!("{}-{}", pool.base, QUOTE),
+            match lines.is_empty() {
+                true => "No limit orders".to_string(),
+                false => lines.join("\n"),
+            },
+            false,
+        )
+    });
+
+    let name = |account: &String| match report.vanity_names.get(account) {
+        Some(vanity) => format!("{} (`{}`)", vanity, shorten_address(account)),
+        None => format!("`{}`", shorten_address(account)),
+    };
+    let list = |accounts: Vec<&String>| match accounts.len() {
+        0 => "None".to_string(),
+        count => {
+            let mut lines = accounts
+                .iter()
+                .take(MAX_LISTED_VALIDATORS)
+                .map(|account| name(account))
+                .collect::<Vec<_>>();
+            if count > MAX_LISTED_VALIDATORS {
+                lines.push(format!("and {} more", count - MAX_LISTED_VALIDATORS));
+            }
+            lines.join("\n")
+        }
+    };
+    let validators = embed("Validators")
+        .field("Epoch", format!("{}", report.epoch), true)
+        .pipe(|it| match &report.auction {
+            Some(auction) => it.field(
+                "Rotations",
+                format!("{}", auction.epochs.saturating_sub(1)),
+                true,
+            ),
+            None => it,
+        })
+        .pipe(|it| match (&report.authorities, previous) {
+            (None, _) => it.field("Authorities", "Unavailable", true),
+            (Some(authorities), Some(previous)) => it
+                .field("Authorities", format!("{}", authorities.len()), true)
+                .field(
+                    "Joined",
+                    list(authorities.difference(previous).collect()),
+                    false,
+                )
+                .field(
+                    "Left",
+                    list(previous.difference(authorities).collect()),
+                    false,
+                ),
+            (Some(authorities), None) => it
+                .field("Authorities", format!("{}", authorities.len()), true)
+                .description("Changes to the authority set are listed from the next report"),
+        });
+
+    let auction = embed("Auction")
+        .field(
+            "Min. Active Bid",
+            format!("{:.3} FLIP", report.min_active_bid),
+            true,
+        )
+        .pipe(|it| match &report.auction {
+            Some(auction) => it
+                .field(
+                    format!("Change ({})", window),
+                    format!(
+                        "{:+.3} FLIP ({})",
+                        auction.last - auction.first,
+                        percent_change(auction.first, auction.last)
+                    ),
+                    true,
+                )
+                .field(
+                    "Range",
+                    format!("{:.3} - {:.3} FLIP", auction.low, auction.high),
+                    true,
+                ),
+            None => it,
+        });
+
+    let health = cf::status_embed(network).title(title("Node Health"));
+    Ok(vec![pools, top_lps, validators, auction, health])
+}
+
+/// Change from `from` to `to` as a signed percentage, or "-" when there is nothing to compare
+/// against.
+fn percent_change(from: f64, to: f64) -> String {
+    match from != 0.0 {
+        true => format!("{:+.2}%", (to - from) / from * 100.0),
+        false => "-".to_string(),
+    }
+}
+
+/// Daily and weekly reports posted on a schedule
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("add", "list", "remove", "preview"),
+    subcommand_required,
+    guild_only,
+    check = "super::digest_access"
+)]
+pub async fn digest(_: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Posts a report to a channel on a cron schedule, in the server's timezone
+///
+/// Examples: `/digest add period:Daily`, `/digest add period:Weekly schedule:30 8 * * 5`
+#[poise::command(prefix_command, slash_command)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "What the report covers"] period: Period,
+    #[description = "Channel to post in, defaults to this one"]
+    #[channel_types("Text")]
+    channel: Option<serenity::GuildChannel>,
+    #[description = "Cron expression (minute hour day month weekday), defaults to 9:00"]
+    schedule: Option<String>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let network = super::network(ctx, network)?;
+    let schedule = schedule.unwrap_or(period.default_schedule().to_string());
+    let schedule = match schedule.parse::<Schedule>() {
+        Ok(schedule) => schedule,
+        Err(err) => {
+            let response = format!(
+                "Invalid schedule: `{}` ({}), expected e.g. `0 9 * * 1`",
+                schedule, err
+            );
+            poise::say_reply(ctx, response).await?;
+            return Ok(());
+        }
+    };
+    let db = &ctx.data().db;
+    if db.guild_digests(guild_id.get())?.len() >= MAX_DIGESTS {
+        let response = format!(
+            "This server already has {} digests, remove one with /digest remove first",
+            MAX_DIGESTS
+        );
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let channel_id = channel.map_or(ctx.channel_id(), |channel| channel.id);
+    let id = db.insert_digest(&Digest {
+        id: 0,
+        guild_id: guild_id.get(),
+        channel_id: channel_id.get(),
+        period: period.key().to_string(),
+        schedule: schedule.to_string(),
+        network: network.name.clone(),
+        last_sent: None,
+        authorities: None,
+    })?;
+    let timezone = ctx.data().settings.get(Some(guild_id))?.timezone();
+    let response = format!(
+        "{} digest `#{}` will be posted in <#{}> on `{}` ({})",
+        period.name(),
+        id,
+        channel_id,
+        schedule,
+        timezone.name()
+    );
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Lists this server's digests
+#[poise::command(prefix_command, slash_command)]
+pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let digests = ctx.data().db.guild_digests(guild_id.get())?;
+    let lines = digests
+        .iter()
+        .map(|digest| {
+            format!(
+                "`#{}` {} in <#{}> on `{}` ({}), last posted {}",
+                digest.id,
+                digest.period,
+                digest.channel_id,
+                digest.schedule,
+                digest.network,
+                digest
+                    .last_sent
+                    .map_or("never".to_string(), |sent| format!("<t:{}:R>", sent))
+            )
+        })
+        .collect::<Vec<_>>();
+    let timezone = ctx.data().settings.get(Some(guild_id))?.timezone();
+    let embed = CreateEmbed::new()
+        .title("Digests")
+        .colour(ctx.data().colours.neutral)
+        .description(match lines.is_empty() {
+            true => "None yet, add one with /digest add".to_string(),
+            false => lines.join("\n"),
+        })
+        .footer(CreateEmbedFooter::new(format!(
+            "Schedules are in {}",
+            timezone.name()
+        )));
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Stops posting a digest
+#[poise::command(prefix_command, slash_command)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "ID of the digest, see /digest list"] id: i64,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Only available in servers")?;
+    let response = match ctx.data().db.remove_digest(guild_id.get(), id)? {
+        true => "Digest removed",
+        false => "No digest with that ID in this server",
+    };
+    poise::say_reply(ctx, response).await?;
+    Ok(())
+}
+
+/// Shows what a digest would look like right now
+///
+/// Example: `/digest preview period:Weekly`
+#[poise::command(prefix_command, slash_command)]
+pub async fn preview(
+    ctx: Context<'_>,
+    #[description = "What the report covers"] period: Period,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let network = super::network(ctx, network)?;
+    let report = {
+        let _permit = super::rpc_permit(ctx).await?;
+        gather(network, &ctx.data().db, period)
+            .await
+            .map_err(|err| format!("Request failed: {err}"))?
+    };
+    let timezone = ctx.data().settings.get(ctx.guild_id())?.timezone();
+    let embeds = render(&report, network, None, timezone)?;
+    ctx.send(poise::CreateReply {
+        embeds,
+        ephemeral: Some(super::ephemeral(ctx)?),
+        ..Default::default()
+    })
+    .await?;
+    Ok(())
+}
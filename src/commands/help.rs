@@ -0,0 +1,145 @@
+use crate::{Context, Data, Error};
+use poise::serenity_prelude::{CreateEmbed, CreateEmbedFooter};
+use tap::pipe::Pipe;
+
+type Command = poise::Command<Data, Error>;
+
+/// Shows what the commands do and how to use them
+///
+/// Examples: `/help`, `/help command:lp orders`
+#[poise::command(prefix_command, slash_command)]
+pub async fn help(
+    ctx: Context<'_>,
+    #[description = "Command or group, e.g. lp orders"]
+    #[autocomplete = "autocomplete_command"]
+    #[rest]
+    command: Option<String>,
+) -> Result<(), Error> {
+    let groups = groups(ctx);
+    let pages = groups
+        .iter()
+        .enumerate()
+        .map(|(i, group)| group_page(ctx, group, i, groups.len()))
+        .collect::<Vec<_>>();
+    let Some(name) = command else {
+        return super::paginate(ctx, pages, 0).await;
+    };
+    let name = name.trim().trim_start_matches('/').to_lowercase();
+    if let Some(i) = groups.iter().position(|group| group.qualified_name == name) {
+        return super::paginate(ctx, pages, i).await;
+    }
+    let Some(command) = find(&ctx.framework().options().commands, &name) else {
+        let response = format!("Unknown command: `{}`, see `/help` for a list", name);
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(command_page(ctx, command))
+            .ephemeral(super::ephemeral(ctx)?),
+    )
+    .await?;
+    Ok(())
+}
+
+async fn autocomplete_command(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    let partial = partial.to_lowercase();
+    let mut names = Vec::new();
+    for group in groups(ctx) {
+        names.push(group.qualified_name.clone());
+        names.extend(visible(&group.subcommands).map(|c| c.qualified_name.clone()));
+    }
+    names
+        .into_iter()
+        .filter(|name| name.contains(&partial))
+        .take(25)
+        .collect()
+}
+
+/// Top level commands usable here, each of which gets its own page.
+fn groups(ctx: Context<'_>) -> Vec<&Command> {
+    visible(&ctx.framework().options().commands)
+        .filter(|command| !command.subcommands.is_empty())
+        .filter(|command| super::admin::enabled_here(ctx, &command.name))
+        .collect()
+}
+
+fn visible(commands: &[Command]) -> impl Iterator<Item = &Command> {
+    commands.iter().filter(|command| !command.hide_in_help)
+}
+
+fn find<'a>(commands: &'a [Command], name: &str) -> Option<&'a Command> {
+    visible(commands).find_map(|command| match command.qualified_name == name {
+        true => Some(command),
+        false => find(&command.subcommands, name),
+    })
+}
+
+/// `/lp orders <asset> [quote_asset]`, with optional arguments in brackets.
+fn usage(command: &Command) -> String {
+    let mut usage = format!("/{}", command.qualified_name);
+    for parameter in &command.parameters {
+        match parameter.required {
+            true => usage += &format!(" <{}>", parameter.name),
+            false => usage += &format!(" [{}]", parameter.name),
+        }
+    }
+    usage
+}
+
+fn group_page(ctx: Context<'_>, group: &Command, page: usize, pages: usize) -> CreateEmbed {
+    visible(&group.subcommands)
+        .fold(
+            CreateEmbed::new()
+                .title(format!("/{}", group.qualified_name))
+                .description(group.description.clone().unwrap_or_default())
+                .colour(ctx.data().colours.neutral),
+            |embed, command| {
+                embed.field(
+                    format!("`{}`", usage(command)),
+                    command.description.clone().unwrap_or_default(),
+                    false,
+                )
+            },
+        )
+        .footer(CreateEmbedFooter::new(format!(
+            "Page {}/{} · /help <command> for details",
+            page + 1,
+            pages
+        )))
+}
+
+fn command_page(ctx: Context<'_>, command: &Command) -> CreateEmbed {
+    let arguments = command
+        .parameters
+        .iter()
+        .map(|parameter| {
+            format!(
+                "`{}`{}: {}",
+                parameter.name,
+                if parameter.required {
+                    ""
+                } else {
+                    " (optional)"
+                },
+                parameter.description.as_deref().unwrap_or("")
+            )
+        })
+        .collect::<Vec<_>>();
+    // The doc comment's first paragraph is the description, and examples follow it.
+    let description = [&command.description, &command.help_text]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    CreateEmbed::new()
+        .title(format!("/{}", command.qualified_name))
+        .description(description)
+        .colour(ctx.data().colours.neutral)
+        .field("Usage", format!("`{}`", usage(command)), false)
+        .pipe(|it| match arguments.is_empty() {
+            true => it,
+            false => it.field("Arguments", arguments.join("\n"), false),
+        })
+}
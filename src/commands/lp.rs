@@ -1,14 +1,25 @@
-use crate::util::util::{asset_in_amount, shorten_address, tick_to_price};
+use crate::analytics::{arbitrage, book, candles, chart, concentration, cross, range};
+use crate::commands::cf::at_block_footer;
+use crate::db::Db;
+use crate::network::Network;
+use crate::rpc::block_at::BlockAt;
+use crate::util::util::{
+    asset_in_amount, get_decimals, parse_duration, price_to_tick, shorten_address, tick_to_price,
+};
 use crate::{Context, Error};
-use jsonrpsee::core::client::ClientT;
 use jsonrpsee::core::Serialize;
 use jsonrpsee::rpc_params;
 use poise::serenity_prelude::{self as serenity};
+use poise::ChoiceParameter;
+use rust_decimal::prelude::*;
 use serde::Deserialize;
-use serenity::Colour;
+use std::cmp::Reverse;
+use tap::pipe::Pipe;
+use time::OffsetDateTime as DateTime;
 use web3::types::U256;
 
-const ASSETS: &'static [&str] = &["USDC", "BTC", "ETH", "DOT", "FLIP"];
+/// Assets the bot knows the decimals of. Each network lists which of them it has pools for.
+pub const ASSETS: &[&str] = &["USDC", "BTC", "ETH", "DOT", "FLIP"];
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(bound = "")]
@@ -57,10 +68,24 @@ pub struct PoolOrders {
     pub range_orders: Vec<RangeOrder>,
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub struct PoolPrice {
+    pub tick: i32,
+}
+
+/// Liquidity pool order books, history and analytics
 #[poise::command(
     prefix_command,
     slash_command,
-    subcommands("orders"),
+    subcommands(
+        "orders",
+        "history",
+        "stats",
+        "range_sim",
+        "concentration",
+        "arb",
+        "cross"
+    ),
     subcommand_required
 )]
 pub async fn lp(_: Context<'_>) -> Result<(), Error> {
@@ -68,81 +93,803 @@ pub async fn lp(_: Context<'_>) -> Result<(), Error> {
 }
 
 /// Lists the top orders for the asset.
+///
+/// Examples: `/lp orders asset:BTC`, `/lp orders asset:ETH at_block:5000000`
 #[poise::command(prefix_command, slash_command)]
 pub async fn orders(
     ctx: Context<'_>,
     #[description = "Base asset"] asset: String,
     #[description = "Quote asset"] quote_asset: Option<String>,
+    #[description = "Query at this block number instead of the latest"] at_block: Option<u32>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
 ) -> Result<(), Error> {
     ctx.defer().await?;
-    if !ASSETS.iter().any(|e| asset.contains(e)) {
+    let network = super::network(ctx, network)?;
+    let base = &asset.to_uppercase();
+    if !network.supports(base) {
         let response = format!("Asset not supported: `{}`", asset);
         poise::say_reply(ctx, response).await?;
         return Ok(());
     }
-    let quote = quote_asset.unwrap_or("USDC".to_string());
-    let orders: PoolOrders = ctx
-        .data()
-        .http_client
-        .request("cf_pool_orders", rpc_params![asset.to_uppercase(), &quote])
+    let quote = &super::quote(quote_asset);
+    let at = BlockAt::resolve(&network.rpc, at_block).await?;
+    // Historical state doesn't change, so only the latest gets a Refresh button.
+    super::interactive(ctx, 0, at.is_latest(), move || async move {
+        let mut orders: PoolOrders = network
+            .rpc
+            .request("cf_pool_orders", rpc_params![base, quote, at.hash])
+            .await
+            .map_err(|err| at.request_error(err))?;
+        // Ranked by price, since the node doesn't list orders in any particular order.
+        orders
+            .limit_orders
+            .bids
+            .sort_by_key(|bid| Reverse(bid.tick));
+        orders.limit_orders.asks.sort_by_key(|ask| ask.tick);
+        let (bids, asks) = (&orders.limit_orders.bids, &orders.limit_orders.asks);
+        if bids.is_empty() && asks.is_empty() {
+            return Err(format!("No limit orders in the {}-{} pool", base, quote).into());
+        }
+        // Page n shows the n-th best bid and ask.
+        let pages = (0..bids.len().max(asks.len()).min(MAX_ORDER_PAGES))
+            .map(|rank| {
+                let bid = bids.get(rank).map(|bid| {
+                    let title = match rank {
+                        0 => format!("Highest Bid {}-{}", base, quote),
+                        _ => format!("Bid #{} {}-{}", rank + 1, base, quote),
+                    };
+                    order_embed(bid, base, quote, quote)
+                        .title(title)
+                        .colour(network.colour)
+                });
+                let ask = asks.get(rank).map(|ask| {
+                    let title = match rank {
+                        0 => format!("Lowest Ask {}-{}", base, quote),
+                        _ => format!("Ask #{} {}-{}", rank + 1, base, quote),
+                    };
+                    order_embed(ask, base, quote, base)
+                        .title(title)
+                        .colour(network.colour)
+                });
+                [bid, ask]
+                    .into_iter()
+                    .flatten()
+                    .map(|it| network.tag(it))
+                    .map(|it| at_block_footer(it, &at))
+                    .collect()
+            })
+            .collect();
+        Ok(pages)
+    })
+    .await
+}
+
+/// Best bid and ask, spread and depth of a pool.
+pub async fn book_embed(
+    network: &Network,
+    base: &str,
+    quote: &str,
+) -> Result<serenity::CreateEmbed, Error> {
+    let orders: PoolOrders = network
+        .rpc
+        .request("cf_pool_orders", rpc_params![base, quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let price: PoolPrice = network
+        .rpc
+        .request("cf_pool_price", rpc_params![base, quote])
         .await
         .map_err(|err| format!("Request failed: {err}"))?;
-    let highest_bid = orders.limit_orders.bids.first().unwrap();
-    let lowest_ask = orders.limit_orders.asks.first().unwrap();
+    let summary = book::summarize(&orders, base, quote, price.tick);
+    let price = |price: Option<f64>| price.map_or("-".to_string(), |price| format!("{:.4}", price));
+    let spread = match (summary.best_bid, summary.best_ask) {
+        (Some(bid), Some(ask)) if summary.mid > 0.0 => {
+            format!("{:.1} bps", (ask - bid) / summary.mid * 10_000.0)
+        }
+        _ => "-".to_string(),
+    };
+    let depth =
+        |bids: f64, asks: f64| format!("Bids: {:.2} {}\nAsks: {:.2} {}", bids, quote, asks, quote);
+    Ok(serenity::CreateEmbed::new()
+        .title(format!("Top of Book {}-{}", base, quote))
+        .colour(network.colour)
+        .pipe(|it| network.tag(it))
+        .field("Best bid", price(summary.best_bid), true)
+        .field("Mid", price(Some(summary.mid)), true)
+        .field("Best ask", price(summary.best_ask), true)
+        .field("Spread", spread, true)
+        .field(
+            "Depth ±1%",
+            depth(summary.bid_depth_1pct, summary.ask_depth_1pct),
+            true,
+        )
+        .field(
+            "Depth ±5%",
+            depth(summary.bid_depth_5pct, summary.ask_depth_5pct),
+            true,
+        ))
+}
+
+/// Orders are paged through by rank, up to this many.
+const MAX_ORDER_PAGES: usize = 25;
+
+/// Fields of a limit order, whose sell amount is in `sell_asset`.
+fn order_embed(
+    order: &LimitOrder,
+    base: &str,
+    quote: &str,
+    sell_asset: &str,
+) -> serenity::CreateEmbed {
+    serenity::CreateEmbed::new()
+        .field("LP", shorten_address(&order.lp), true)
+        .field("ID", format!("{}", order.id), true)
+        .field("Tick", format!("{}", order.tick), true)
+        .field(
+            "Price",
+            format!("{}", tick_to_price(order.tick, base, quote)),
+            true,
+        )
+        .field(
+            "Sell amount",
+            format!(
+                "{}",
+                asset_in_amount(&order.sell_amount, sell_asset).round_dp(4)
+            ),
+            true,
+        )
+        .field("Fees earned", format!("{}", order.fees_earned), true)
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy, Debug)]
+pub enum Interval {
+    #[name = "1m"]
+    Minute,
+    #[name = "1h"]
+    Hour,
+    #[name = "1d"]
+    Day,
+}
+
+impl Interval {
+    pub fn seconds(self) -> i64 {
+        match self {
+            Interval::Minute => 60,
+            Interval::Hour => 3600,
+            Interval::Day => 86400,
+        }
+    }
+
+    fn default_range(self) -> i64 {
+        self.seconds() * 48
+    }
+}
+
+/// Shows OHLC candles of the pool mid price from recorded snapshots.
+///
+/// Example: `/lp history base:ETH interval:1h range:2d`
+#[poise::command(prefix_command, slash_command)]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "Base asset"] base: String,
+    #[description = "Quote asset"] quote: Option<String>,
+    #[description = "Candle interval"] interval: Option<Interval>,
+    #[description = "How far back, e.g. 6h, 2d"] range: Option<String>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let base = base.to_uppercase();
+    let quote = super::quote(quote);
+    let network = super::network(ctx, network)?;
+    if !network.supports(&base) || !network.supports(&quote) {
+        let response = format!("Pool not supported: `{}-{}`", base, quote);
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    if !network.recorded {
+        let response = format!(
+            "History is only recorded for `{}`",
+            ctx.data().networks.default().name
+        );
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let interval = interval.unwrap_or(Interval::Hour);
+    let range_seconds = match range {
+        Some(range) => parse_duration(&range).ok_or(format!(
+            "Invalid range: `{}`, expected e.g. 6h or 2d",
+            range
+        ))?,
+        None => interval.default_range(),
+    };
+    let now = DateTime::now_utc().unix_timestamp();
+    let snapshots = ctx
+        .data()
+        .db
+        .pool_snapshots(&base, &quote, now - range_seconds, now + 1)?;
+    let candles = candles::build(&snapshots, interval.seconds());
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        let response = format!("No recorded data for `{}-{}` in that range", base, quote);
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    };
+    let high = candles.iter().map(|c| c.high).fold(f64::MIN, f64::max);
+    let low = candles.iter().map(|c| c.low).fold(f64::MAX, f64::min);
+    let change = (last.close - first.open) / first.open * 100.0;
+    let since = super::display_time(ctx, DateTime::from_unix_timestamp(first.start)?)?;
+    let png = chart::candlestick_png(&candles, 800, 400)?;
     ctx.send(
         poise::CreateReply::default()
+            .attachment(serenity::CreateAttachment::bytes(png, "history.png"))
             .embed(
                 serenity::CreateEmbed::new()
-                    .title(format!("Highest Bid {}-{}", asset.to_uppercase(), &quote))
-                    .colour(Colour::DARK_GREEN)
-                    .field("LP", format!("{}", shorten_address(&highest_bid.lp)), true)
-                    .field("ID", format!("{}", highest_bid.id), true)
-                    .field("Tick", format!("{}", highest_bid.tick), true)
+                    .title(format!("{}-{} {} candles", base, quote, interval.name()))
+                    .colour(network.colour)
+                    .pipe(|it| network.tag(it))
+                    .field("Open", format!("{:.4}", first.open), true)
+                    .field("Close", format!("{:.4}", last.close), true)
+                    .field("Change", format!("{:+.2}%", change), true)
+                    .field("High", format!("{:.4}", high), true)
+                    .field("Low", format!("{:.4}", low), true)
                     .field(
-                        "Price",
-                        format!(
-                            "{}",
-                            tick_to_price(highest_bid.tick, &asset.to_uppercase(), &quote)
-                        ),
+                        "Avg. spread",
+                        candles::average_spread_bps(&snapshots)
+                            .map_or("-".to_string(), |bps| format!("{:.1} bps", bps)),
                         true,
                     )
+                    .image("attachment://history.png")
+                    .footer(serenity::CreateEmbedFooter::new(format!(
+                        "{} candles since {}",
+                        candles.len(),
+                        since
+                    ))),
+            )
+            .ephemeral(super::ephemeral(ctx)?),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Estimates traded volume and LP fee revenue for a pool from recorded order book changes.
+///
+/// Example: `/lp stats base:BTC`
+#[poise::command(prefix_command, slash_command)]
+pub async fn stats(
+    ctx: Context<'_>,
+    #[description = "Base asset"] base: String,
+    #[description = "Quote asset"] quote: Option<String>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    let base = base.to_uppercase();
+    let quote = super::quote(quote);
+    let network = super::network(ctx, network)?;
+    if !network.supports(&base) || !network.supports(&quote) {
+        let response = format!("Pool not supported: `{}-{}`", base, quote);
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    if !network.recorded {
+        let response = format!(
+            "History is only recorded for `{}`",
+            ctx.data().networks.default().name
+        );
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let db = &ctx.data().db;
+    let now = DateTime::now_utc().unix_timestamp();
+    let hour = db.pool_fills_since(&base, &quote, now - 3600)?;
+    let day = db.pool_fills_since(&base, &quote, now - 86400)?;
+    let week = db.pool_fills_since(&base, &quote, now - 7 * 86400)?;
+    let days = recorded_days(db, &base, &quote, now)?;
+    let daily = |total: f64| days.map_or("-".to_string(), |days| format!("{:.2}", total / days));
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(
+                serenity::CreateEmbed::new()
+                    .title(format!("Pool Stats {}-{}", base, quote))
+                    .colour(network.colour)
+                    .pipe(|it| network.tag(it))
+                    .field("Volume (1h)", format!("{:.2} {}", hour.volume, quote), true)
+                    .field("Volume (24h)", format!("{:.2} {}", day.volume, quote), true)
+                    .field(
+                        "Avg. daily volume (7d)",
+                        format!("{} {}", daily(week.volume), quote),
+                        true,
+                    )
+                    .field("Fees (1h)", format!("{:.2} {}", hour.fees(), quote), true)
                     .field(
-                        "Sell amount",
+                        "Fees (24h)",
                         format!(
-                            "{}",
-                            asset_in_amount(&highest_bid.sell_amount, &quote).round_dp(4)
+                            "{:.2} {}\nLimit: {:.2}\nRange: {:.2}",
+                            day.fees(),
+                            quote,
+                            day.limit_fees,
+                            day.range_fees
                         ),
                         true,
                     )
-                    .field("Fees earned", format!("{}", highest_bid.fees_earned), true),
+                    .field(
+                        "Avg. daily fees (7d)",
+                        format!("{} {}", daily(week.fees()), quote),
+                        true,
+                    )
+                    .footer(serenity::CreateEmbedFooter::new(
+                        "Estimated from order book changes, daily averages over the days recorded",
+                    )),
+            )
+            .ephemeral(super::ephemeral(ctx)?),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Days of the last week a pool has been recorded for, to average weekly totals over. At least
+/// an hour, so a fresh recording isn't extrapolated from a few blocks.
+fn recorded_days(db: &Db, base: &str, quote: &str, now: i64) -> Result<Option<f64>, Error> {
+    let first = db.first_pool_snapshot_since(base, quote, now - 7 * 86400)?;
+    Ok(first.map(|first| (now - first).max(3600) as f64 / 86400.0))
+}
+
+const PRICE_MOVES: &[f64] = &[-0.5, -0.25, -0.1, -0.05, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// Simulates a range order: liquidity, estimated fee APR and impermanent loss.
+///
+/// Example: `/lp range_sim base:ETH lower_price:3000 upper_price:4000 amount:10000`
+#[poise::command(prefix_command, slash_command, rename = "range-sim")]
+pub async fn range_sim(
+    ctx: Context<'_>,
+    #[description = "Base asset"] base: String,
+    #[description = "Lower price of the range"] lower_price: f64,
+    #[description = "Upper price of the range"] upper_price: f64,
+    #[description = "Amount to deploy, valued in the quote asset"] amount: f64,
+    #[description = "Quote asset"] quote: Option<String>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let base = base.to_uppercase();
+    let quote = super::quote(quote);
+    let network = super::network(ctx, network)?;
+    let _permit = super::rpc_permit(ctx).await?;
+    if !network.supports(&base) || !network.supports(&quote) {
+        let response = format!("Pool not supported: `{}-{}`", base, quote);
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    if !(0.0 < lower_price && lower_price < upper_price) || amount <= 0.0 {
+        poise::say_reply(
+            ctx,
+            "Expected 0 < lower price < upper price and a positive amount",
+        )
+        .await?;
+        return Ok(());
+    }
+    let range = Range {
+        start: price_to_tick(lower_price, &base, &quote),
+        end: price_to_tick(upper_price, &base, &quote),
+    };
+    if range.start >= range.end {
+        poise::say_reply(
+            ctx,
+            "The lower and upper price round to the same tick, widen the range",
+        )
+        .await?;
+        return Ok(());
+    }
+    let rpc = &network.rpc;
+    let price: PoolPrice = rpc
+        .request("cf_pool_price", rpc_params![&base, &quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let ratio: PoolPairsMap = rpc
+        .request(
+            "cf_required_asset_ratio_for_range_order",
+            rpc_params![&base, &quote, &range],
+        )
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let orders: PoolOrders = rpc
+        .request("cf_pool_orders", rpc_params![&base, &quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+
+    // Split the amount between the assets in the ratio the range requires.
+    let current_price = tick_to_price(price.tick, &base, &quote) as f64;
+    let ratio_base = asset_in_amount(&ratio.base, &base)
+        .to_f64()
+        .unwrap_or_default();
+    let ratio_quote = asset_in_amount(&ratio.quote, &quote)
+        .to_f64()
+        .unwrap_or_default();
+    let ratio_value = ratio_base * current_price + ratio_quote;
+    if ratio_value <= 0.0 {
+        poise::say_reply(ctx, "The node returned an empty asset ratio for that range").await?;
+        return Ok(());
+    }
+    let base_amount = ratio_base * amount / ratio_value;
+    let quote_amount = ratio_quote * amount / ratio_value;
+    let liquidity = range::liquidity_for_amounts(
+        &range,
+        range::sqrt_price_at(price.tick),
+        base_amount * 10_f64.powi(get_decimals(&base)),
+        quote_amount * 10_f64.powi(get_decimals(&quote)),
+    );
+    let order = RangeOrder {
+        lp: String::new(),
+        id: U256::zero(),
+        range: range.clone(),
+        liquidity: liquidity as u128,
+        fees_earned: PoolPairsMap {
+            base: U256::zero(),
+            quote: U256::zero(),
+        },
+    };
+
+    // Fee APR assumes the position stays in range and earns its share of recorded range fees.
+    let db = &ctx.data().db;
+    let now = DateTime::now_utc().unix_timestamp();
+    let (week, snapshots, days) = match network.recorded {
+        true => (
+            db.pool_fills_since(&base, &quote, now - 7 * 86400)?,
+            db.pool_snapshots(&base, &quote, now - 7 * 86400, now + 1)?,
+            recorded_days(db, &base, &quote, now)?,
+        ),
+        false => Default::default(),
+    };
+    let samples: u32 = snapshots.iter().map(|s| s.samples).sum();
+    let pool_liquidity = match samples {
+        0 => book::summarize(&orders, &base, &quote, price.tick).range_liquidity,
+        _ => {
+            snapshots
+                .iter()
+                .map(|s| s.range_liquidity * s.samples as f64)
+                .sum::<f64>()
+                / samples as f64
+        }
+    };
+    let share = range::share(liquidity, pool_liquidity);
+    let fee_apr = match days {
+        Some(days) if week.range_fees > 0.0 => format!(
+            "{:.2}%",
+            week.range_fees / days * 365.0 * share / amount * 100.0
+        ),
+        _ => "No recorded range fees".to_string(),
+    };
+    let in_range = range.start <= price.tick && price.tick < range.end;
+
+    let moves = range::price_moves(&order, price.tick, PRICE_MOVES)
+        .iter()
+        .map(|m| {
+            format!(
+                "{:+.0}%: {:.4} ({:+.2}% vs hold)",
+                m.change * 100.0,
+                current_price * (1.0 + m.change),
+                m.impermanent_loss() * 100.0
             )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    ctx.send(
+        poise::CreateReply::default()
             .embed(
                 serenity::CreateEmbed::new()
-                    .title(format!("Lowest Ask {}-{}", asset.to_uppercase(), &quote))
-                    .colour(Colour::DARK_RED)
-                    .field("LP", format!("{}", shorten_address(&lowest_ask.lp)), true)
-                    .field("ID", format!("{}", lowest_ask.id), true)
-                    .field("Tick", format!("{}", lowest_ask.tick), true)
+                    .title(format!("Range Simulation {}-{}", base, quote))
+                    .colour(network.colour)
+                    .pipe(|it| network.tag(it))
                     .field(
-                        "Price",
+                        "Range",
                         format!(
-                            "{}",
-                            tick_to_price(lowest_ask.tick, &asset.to_uppercase(), &quote)
+                            "{:.4} - {:.4}\nTicks {} to {}",
+                            lower_price, upper_price, range.start, range.end
                         ),
                         true,
                     )
                     .field(
-                        "Sell amount",
+                        "Current price",
                         format!(
-                            "{}",
-                            asset_in_amount(&lowest_ask.sell_amount, &asset.to_uppercase())
-                                .round_dp(4)
+                            "{:.4}\n{}",
+                            current_price,
+                            match in_range {
+                                true => "In range",
+                                false => "Out of range",
+                            }
                         ),
                         true,
                     )
-                    .field("Fees earned", format!("{}", lowest_ask.fees_earned), true),
+                    .field(
+                        "Deposit",
+                        format!("{:.4} {}\n{:.4} {}", base_amount, base, quote_amount, quote),
+                        true,
+                    )
+                    .field("Liquidity", format!("{}", order.liquidity), true)
+                    .field(
+                        "Share of active liquidity",
+                        format!("{:.4}%", share * 100.0),
+                        true,
+                    )
+                    .field("Est. fee APR (7d)", fee_apr, true)
+                    .field("Price moves (impermanent loss)", moves, false),
+            )
+            .ephemeral(super::ephemeral(ctx)?),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Shows how concentrated a pool's liquidity is among LPs.
+///
+/// Example: `/lp concentration base:BTC window:7d`
+#[poise::command(prefix_command, slash_command)]
+pub async fn concentration(
+    ctx: Context<'_>,
+    #[description = "Base asset"] base: String,
+    #[description = "Quote asset"] quote: Option<String>,
+    #[description = "Window for best bid/ask holders, e.g. 6h, 7d"] window: Option<String>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let base = base.to_uppercase();
+    let quote = super::quote(quote);
+    let network = super::network(ctx, network)?;
+    let permit = super::rpc_permit(ctx).await?;
+    if !network.supports(&base) || !network.supports(&quote) {
+        let response = format!("Pool not supported: `{}-{}`", base, quote);
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let window_seconds = match window {
+        Some(window) => parse_duration(&window).ok_or(format!(
+            "Invalid window: `{}`, expected e.g. 6h or 7d",
+            window
+        ))?,
+        None => 86400,
+    };
+    let rpc = &network.rpc;
+    let orders: PoolOrders = rpc
+        .request("cf_pool_orders", rpc_params![&base, &quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let price: PoolPrice = rpc
+        .request("cf_pool_price", rpc_params![&base, &quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let concentration = concentration::analyze(&orders, &base, &quote, price.tick);
+
+    // Old snapshots are kept in hourly buckets, so align the window to the hour for counts and
+    // samples to match.
+    let db = &ctx.data().db;
+    let from = DateTime::now_utc().unix_timestamp() - window_seconds;
+    let from = from - from.rem_euclid(3600);
+    let (counts, samples) = match network.recorded {
+        true => (
+            db.best_quote_counts_since(&base, &quote, from)?,
+            db.pool_snapshot_samples_since(&base, &quote, from)?,
+        ),
+        false => Default::default(),
+    };
+    let frequency = |count: u32| match samples {
+        0 => "-".to_string(),
+        _ => format!("{:.0}%", count as f64 / samples as f64 * 100.0),
+    };
+    let market = match concentration.hhi {
+        hhi if hhi < 1500.0 => "competitive",
+        hhi if hhi < 2500.0 => "moderately concentrated",
+        _ => "highly concentrated",
+    };
+    drop(permit);
+    let embed = |top_lps: String| {
+        serenity::CreateEmbed::new()
+            .title(format!("Liquidity Concentration {}-{}", base, quote))
+            .colour(network.colour)
+            .pipe(|it| network.tag(it))
+            .field(
+                "Distinct LPs",
+                format!("{}", concentration.distinct_lps),
+                true,
+            )
+            .field(
+                "HHI (±1% depth)",
+                format!("{:.0} ({})", concentration.hhi, market),
+                true,
             )
-            .ephemeral(false),
+            .field("Top LPs", top_lps, false)
+            .footer(serenity::CreateEmbedFooter::new(format!(
+                "Best bid/ask held over {} recorded snapshots",
+                samples
+            )))
+    };
+    let mut pages = concentration
+        .shares
+        .iter()
+        .enumerate()
+        .map(|(rank, share)| {
+            let held = counts.get(&share.lp).copied().unwrap_or_default();
+            format!(
+                "{}. `{}` ToB {:.1}% | ±1% {:.1}% | best bid {} | best ask {}",
+                rank + 1,
+                shorten_address(&share.lp),
+                share.top_of_book * 100.0,
+                share.depth_1pct * 100.0,
+                frequency(held.bid),
+                frequency(held.ask)
+            )
+        })
+        .collect::<Vec<_>>()
+        .chunks(10)
+        .map(|lps| embed(lps.join("\n")))
+        .collect::<Vec<_>>();
+    if pages.is_empty() {
+        pages.push(embed("No limit orders".to_string()));
+    }
+    super::paginate(ctx, pages, 0).await
+}
+
+/// Compares the pool price with the reference price feed.
+///
+/// Example: `/lp arb base:ETH`
+#[poise::command(prefix_command, slash_command)]
+pub async fn arb(
+    ctx: Context<'_>,
+    #[description = "Base asset"] base: String,
+    #[description = "Quote asset"] quote: Option<String>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let base = base.to_uppercase();
+    let quote = super::quote(quote);
+    let network = super::network(ctx, network)?;
+    let _permit = super::rpc_permit(ctx).await?;
+    if !network.supports(&base) || !network.supports(&quote) {
+        let response = format!("Pool not supported: `{}-{}`", base, quote);
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let Some(feed) = &ctx.data().price_feed else {
+        poise::say_reply(ctx, "No reference price feed is configured").await?;
+        return Ok(());
+    };
+    let reference = feed
+        .price(&base, &quote)
+        .await
+        .map_err(|err| format!("Price feed failed: {err}"))?;
+    let rpc = &network.rpc;
+    let orders: PoolOrders = rpc
+        .request("cf_pool_orders", rpc_params![&base, &quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let price: PoolPrice = rpc
+        .request("cf_pool_price", rpc_params![&base, &quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let summary = book::summarize(&orders, &base, &quote, price.tick);
+    let quoted = |price: Option<f64>| match price {
+        Some(price) => format!(
+            "{:.4} ({:+.1} bps)",
+            price,
+            arbitrage::deviation_bps(price, reference)
+        ),
+        None => "-".to_string(),
+    };
+    let opportunity = match arbitrage::opportunity(&orders, &base, &quote, price.tick, reference) {
+        Some(arb) => format!(
+            "{} {:.4} {} for {:.2} {}\nEst. profit: {:.2} {}",
+            match arb.direction {
+                arbitrage::Direction::Buy => "Buy",
+                arbitrage::Direction::Sell => "Sell",
+            },
+            arb.base_amount,
+            base,
+            arb.quote_amount,
+            quote,
+            arb.profit,
+            quote
+        ),
+        None => "None, the reference is inside the spread".to_string(),
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(
+                serenity::CreateEmbed::new()
+                    .title(format!("Reference Price {}-{}", base, quote))
+                    .colour(network.colour)
+                    .pipe(|it| network.tag(it))
+                    .field("Reference", format!("{:.4}", reference), true)
+                    .field("Mid", quoted(Some(summary.mid)), true)
+                    .field("\u{200b}", "\u{200b}", true)
+                    .field("Best bid", quoted(summary.best_bid), true)
+                    .field("Best ask", quoted(summary.best_ask), true)
+                    .field("\u{200b}", "\u{200b}", true)
+                    .field("Arbitrage until converged", opportunity, false),
+            )
+            .ephemeral(super::ephemeral(ctx)?),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Shows the implied rate between two assets routed through their USDC pools.
+///
+/// Example: `/lp cross a:BTC b:ETH size:0.5`
+#[poise::command(prefix_command, slash_command)]
+pub async fn cross(
+    ctx: Context<'_>,
+    #[description = "Asset to price"] a: String,
+    #[description = "Asset to price it in"] b: String,
+    #[description = "Size in the first asset for depth adjusted rates"] size: Option<f64>,
+    #[description = "Network, defaults to the server's"]
+    #[autocomplete = "super::autocomplete_network"]
+    network: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let (a, b, quote) = (a.to_uppercase(), b.to_uppercase(), "USDC");
+    let network = super::network(ctx, network)?;
+    let _permit = super::rpc_permit(ctx).await?;
+    if a == b
+        || [&a, &b]
+            .iter()
+            .any(|asset| *asset == quote || !network.supports(asset))
+    {
+        let response = format!(
+            "Expected two different non-{} assets, got `{}` and `{}`",
+            quote, a, b
+        );
+        poise::say_reply(ctx, response).await?;
+        return Ok(());
+    }
+    let rpc = &network.rpc;
+    let a_orders: PoolOrders = rpc
+        .request("cf_pool_orders", rpc_params![&a, quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let b_orders: PoolOrders = rpc
+        .request("cf_pool_orders", rpc_params![&b, quote])
+        .await
+        .map_err(|err| format!("Request failed: {err}"))?;
+    let rate = |r: Option<f64>| r.map_or("No liquidity".to_string(), |r| format!("{:.6}", r));
+    let top = cross::top_of_book(&a_orders, &b_orders, &a, &b, quote);
+    let mut embed = serenity::CreateEmbed::new()
+        .title(format!("Cross Rate {}-{} via {}", a, b, quote))
+        .colour(network.colour)
+        .pipe(|it| network.tag(it))
+        .field("Bid", rate(top.bid), true)
+        .field("Ask", rate(top.ask), true)
+        .field(
+            "Spread",
+            top.spread_bps()
+                .map_or("-".to_string(), |bps| format!("{:.1} bps", bps)),
+            true,
+        );
+    if let Some(size) = size.filter(|size| *size > 0.0) {
+        let sized = cross::for_size(&a_orders, &b_orders, &a, &b, quote, size);
+        embed = embed
+            .field(format!("Bid for {} {}", size, a), rate(sized.bid), true)
+            .field(format!("Ask for {} {}", size, a), rate(sized.ask), true)
+            .field(
+                "Spread",
+                sized
+                    .spread_bps()
+                    .map_or("-".to_string(), |bps| format!("{:.1} bps", bps)),
+                true,
+            );
+    }
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(embed.footer(serenity::CreateEmbedFooter::new(format!(
+                "{} per {}, from limit orders in both legs",
+                b, a
+            ))))
+            .ephemeral(super::ephemeral(ctx)?),
     )
     .await?;
     Ok(())
@@ -1,2 +1,419 @@
+pub mod admin;
+pub mod alerts;
 pub mod cf;
+pub mod config;
+pub mod dashboard;
+pub mod digest;
+pub mod help;
 pub mod lp;
+
+use crate::config::{Cooldown, Cooldowns, RpcLimitSettings};
+use crate::network::Network;
+use crate::tasks::recorder::QUOTE;
+use crate::{Context, Data, Error};
+use poise::serenity_prelude::{
+    ButtonStyle, ComponentInteractionCollector, CreateActionRow, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, RoleId,
+};
+use poise::{FrameworkError, PartialContext};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use time::format_description;
+use time::OffsetDateTime as DateTime;
+use time_tz::{OffsetDateTimeExt, TimeZone, Tz};
+
+/// Resolves a command's `network` argument, falling back to the guild's default and then to
+/// the configured one.
+pub fn network(ctx: Context<'_>, name: Option<String>) -> Result<&Network, Error> {
+    let networks = &ctx.data().networks;
+    if let Some(name) = name {
+        return networks.get(&name).ok_or_else(|| {
+            format!(
+                "Unknown network: `{}`, expected one of {}",
+                name,
+                networks.names().join(", ")
+            )
+            .into()
+        });
+    }
+    let guild_default = ctx.data().settings.get(ctx.guild_id())?.network;
+    // A guild may still point at a network that was removed from the config since.
+    Ok(guild_default
+        .and_then(|name| networks.get(&name))
+        .unwrap_or(networks.default()))
+}
+
+/// Resolves a command's quote asset argument. Pools are only quoted in [`QUOTE`], so it
+/// isn't a server setting.
+pub fn quote(quote: Option<String>) -> String {
+    quote.map_or(QUOTE.to_string(), |quote| quote.to_uppercase())
+}
+
+/// Whether replies should only be visible to the caller in this guild.
+pub fn ephemeral(ctx: Context<'_>) -> Result<bool, Error> {
+    Ok(ctx.data().settings.get(ctx.guild_id())?.ephemeral)
+}
+
+/// Formats a time in the guild's display timezone.
+pub fn display_time(ctx: Context<'_>, at: DateTime) -> Result<String, Error> {
+    format_time(at, ctx.data().settings.get(ctx.guild_id())?.timezone())
+}
+
+pub fn format_time(at: DateTime, timezone: &Tz) -> Result<String, Error> {
+    let format = format_description::parse_borrowed::<2>(cf::DATE_FORMAT)?;
+    Ok(format!(
+        "{} {}",
+        at.to_timezone(timezone).format(&format)?,
+        timezone.name()
+    ))
+}
+
+/// How long buttons keep working after the last press.
+const BUTTON_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Refreshing more often than once a block would only show the same data.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(6);
+
+/// Sends `pages` starting at `start`, with Prev/Next buttons for the caller.
+pub async fn paginate(
+    ctx: Context<'_>,
+    pages: Vec<CreateEmbed>,
+    start: usize,
+) -> Result<(), Error> {
+    let pages = pages.into_iter().map(|page| vec![page]).collect::<Vec<_>>();
+    interactive(ctx, start, false, || {
+        let pages = pages.clone();
+        async move { Ok(pages) }
+    })
+    .await
+}
+
+/// Sends the pages `render` returns, each made of one or more embeds. Several pages get
+/// Prev/Next buttons and `refresh` adds a Refresh button that renders them again and edits the
+/// message in place. Only the caller can press them, and they are disabled once the caller
+/// stops using them.
+pub async fn interactive<F, Fut>(
+    ctx: Context<'_>,
+    start: usize,
+    refresh: bool,
+    render: F,
+) -> Result<(), Error>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<Vec<Vec<CreateEmbed>>, Error>>,
+{
+    let mut pages = render().await?;
+    if pages.is_empty() {
+        return Err("Nothing to show".into());
+    }
+    let mut page = start.min(pages.len() - 1);
+    let mut rendered_at = Instant::now();
+
+    let id = ctx.id();
+    let [prev, next, reload] = ["prev", "next", "refresh"].map(|button| format!("{id}:{button}"));
+    let components = |page: usize, pages: usize, enabled: bool| {
+        let mut buttons = Vec::new();
+        if pages > 1 {
+            buttons.push(
+                CreateButton::new(&prev)
+                    .label("Prev")
+                    .disabled(!enabled || page == 0),
+            );
+            buttons.push(
+                CreateButton::new(format!("{id}:page"))
+                    .label(format!("{}/{}", page + 1, pages))
+                    .style(ButtonStyle::Secondary)
+                    .disabled(true),
+            );
+            buttons.push(
+                CreateButton::new(&next)
+                    .label("Next")
+                    .disabled(!enabled || page + 1 >= pages),
+            );
+        }
+        if refresh {
+            buttons.push(
+                CreateButton::new(&reload)
+                    .label("Refresh")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(!enabled),
+            );
+        }
+        match buttons.is_empty() {
+            true => Vec::new(),
+            false => vec![CreateActionRow::Buttons(buttons)],
+        }
+    };
+
+    let reply = poise::CreateReply {
+        embeds: pages[page].clone(),
+        components: Some(components(page, pages.len(), true)),
+        ..Default::default()
+    };
+    let handle = ctx.send(reply.ephemeral(ephemeral(ctx)?)).await?;
+    if pages.len() <= 1 && !refresh {
+        return Ok(());
+    }
+
+    while let Some(press) = ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id.starts_with(&format!("{id}:")))
+        .timeout(BUTTON_TIMEOUT)
+        .await
+    {
+        if press.user.id != ctx.author().id {
+            let response = CreateInteractionResponseMessage::new()
+                .content("Only the invoker can page this")
+                .ephemeral(true);
+            press
+                .create_response(ctx, CreateInteractionResponse::Message(response))
+                .await?;
+            continue;
+        }
+        if press.data.custom_id == next {
+            page = (page + 1).min(pages.len() - 1);
+        } else if press.data.custom_id == prev {
+            page = page.saturating_sub(1);
+        } else if press.data.custom_id == reload && rendered_at.elapsed() >= MIN_REFRESH_INTERVAL {
+            match render().await {
+                Ok(rendered) if !rendered.is_empty() => {
+                    pages = rendered;
+                    page = page.min(pages.len() - 1);
+                    rendered_at = Instant::now();
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    let response = CreateInteractionResponseMessage::new()
+                        .content(format!("Refresh failed: {err}"))
+                        .ephemeral(true);
+                    press
+                        .create_response(ctx, CreateInteractionResponse::Message(response))
+                        .await?;
+                    continue;
+                }
+            }
+        }
+        let update = CreateInteractionResponseMessage::new()
+            .embeds(pages[page].clone())
+            .components(components(page, pages.len(), true));
+        press
+            .create_response(ctx, CreateInteractionResponse::UpdateMessage(update))
+            .await?;
+    }
+    let reply = poise::CreateReply {
+        embeds: pages[page].clone(),
+        components: Some(components(page, pages.len(), false)),
+        ..Default::default()
+    };
+    handle.edit(ctx, reply).await?;
+    Ok(())
+}
+
+pub async fn autocomplete_network(ctx: Context<'_>, partial: &str) -> Vec<String> {
+    ctx.data()
+        .networks
+        .names()
+        .into_iter()
+        .filter(|name| name.starts_with(partial))
+        .map(String::from)
+        .collect()
+}
+
+/// The guild's prefix for prefix commands, or the configured one.
+pub async fn prefix(ctx: PartialContext<'_, Data, Error>) -> Result<Option<String>, Error> {
+    let prefix = ctx.data.settings.get(ctx.guild_id)?.prefix;
+    Ok(Some(prefix.unwrap_or(ctx.data.default_prefix.clone())))
+}
+
+/// Runs before every command.
+pub async fn command_check(ctx: Context<'_>) -> Result<bool, Error> {
+    Ok(admin::group_enabled(ctx)? && channel_allowed(ctx).await?)
+}
+
+/// Rejects commands outside the guild's allowed channels. `/config` stays usable anywhere so
+/// admins can't lock themselves out.
+async fn channel_allowed(ctx: Context<'_>) -> Result<bool, Error> {
+    if ctx.command().qualified_name.starts_with("config") {
+        return Ok(true);
+    }
+    let allowed = ctx.data().settings.get(ctx.guild_id())?.allowed_channels;
+    if allowed.is_empty() || allowed.contains(&ctx.channel_id().get()) {
+        return Ok(true);
+    }
+    let channels = allowed
+        .iter()
+        .map(|id| format!("<#{}>", id))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(format!("Commands can only be used in {} here", channels).into())
+}
+
+/// Limits `/config` to server managers and members with the role set for it.
+pub async fn config_access(ctx: Context<'_>) -> Result<bool, Error> {
+    role_gate(ctx, "config", true).await
+}
+
+/// Limits `/alerts` to members with the role set for it, open to everyone when unset.
+pub async fn alerts_access(ctx: Context<'_>) -> Result<bool, Error> {
+    role_gate(ctx, "alerts", false).await
+}
+
+/// Limits `/dashboard` like `/config`, since dashboards post messages that stay around.
+pub async fn dashboard_access(ctx: Context<'_>) -> Result<bool, Error> {
+    role_gate(ctx, "dashboard", true).await
+}
+
+/// Limits `/digest` like `/config`, since digests keep posting to channels.
+pub async fn digest_access(ctx: Context<'_>) -> Result<bool, Error> {
+    role_gate(ctx, "digest", true).await
+}
+
+/// Members with Manage Server always pass so a deleted role can't lock a group.
+async fn role_gate(ctx: Context<'_>, group: &str, managers_only: bool) -> Result<bool, Error> {
+    let Some(member) = ctx.author_member().await else {
+        return Ok(!managers_only);
+    };
+    let manager = member
+        .permissions
+        .or_else(|| ctx.guild().map(|guild| guild.member_permissions(&member)))
+        .is_some_and(|permissions| permissions.manage_guild());
+    if manager {
+        return Ok(true);
+    }
+    match ctx.data().settings.get(ctx.guild_id())?.roles.get(group) {
+        Some(&role) if member.roles.contains(&RoleId::new(role)) => Ok(true),
+        Some(role) => Err(format!("You need the <@&{}> role to use `/{}`", role, group).into()),
+        None if managers_only => {
+            Err(format!("You need the Manage Server permission to use `/{}`", group).into())
+        }
+        None => Ok(true),
+    }
+}
+
+/// Marks every prefix command, subcommands included, the way poise's `track_edits` attribute
+/// does: edited invocations re-run and edit the reply, deleted ones delete it.
+pub fn track_edits(commands: &mut [poise::Command<Data, Error>]) {
+    for command in commands {
+        if command.prefix_action.is_some() {
+            command.invoke_on_edit = true;
+            command.track_deletion = true;
+            command.reuse_response = true;
+        }
+        track_edits(&mut command.subcommands);
+    }
+}
+
+/// Sets the configured cooldowns on every command, returning the configured names that
+/// don't match one.
+pub fn apply_cooldowns(
+    commands: &[poise::Command<Data, Error>],
+    cooldowns: &Cooldowns,
+) -> Vec<String> {
+    fn apply(
+        commands: &[poise::Command<Data, Error>],
+        parent: Option<&str>,
+        cooldowns: &Cooldowns,
+        matched: &mut Vec<String>,
+    ) {
+        for command in commands {
+            let name = match parent {
+                Some(parent) => format!("{} {}", parent, command.name),
+                None => command.name.clone(),
+            };
+            let configured = cooldowns.commands.iter().find(|(n, _)| *n == name);
+            if let Some((name, _)) = configured {
+                matched.push(name.clone());
+            }
+            // Groups only dispatch, so a default on them would double up with their subcommands.
+            let cooldown: Cooldown = match configured {
+                Some((_, cooldown)) => *cooldown,
+                None if command.subcommands.is_empty() => cooldowns.default,
+                None => Cooldown::default(),
+            };
+            let mut config = command.cooldown_config.write().unwrap();
+            config.user = cooldown.user;
+            config.global = cooldown.global;
+            drop(config);
+            apply(&command.subcommands, Some(&name), cooldowns, matched);
+        }
+    }
+    let mut matched = Vec::new();
+    apply(commands, None, cooldowns, &mut matched);
+    cooldowns
+        .commands
+        .iter()
+        .map(|(name, _)| name)
+        .filter(|name| !matched.contains(name))
+        .cloned()
+        .collect()
+}
+
+/// Bounds how many RPC heavy commands run at once, so a burst of them can't starve the
+/// node pool for background tasks.
+#[derive(Debug)]
+pub struct RpcLimit {
+    permits: Arc<tokio::sync::Semaphore>,
+    wait: Duration,
+}
+
+impl RpcLimit {
+    pub fn new(settings: RpcLimitSettings) -> RpcLimit {
+        RpcLimit {
+            permits: Arc::new(tokio::sync::Semaphore::new(settings.max_concurrent)),
+            wait: settings.wait,
+        }
+    }
+}
+
+/// Waits for a slot to make RPC requests in, held until the returned permit is dropped.
+pub async fn rpc_permit(ctx: Context<'_>) -> Result<tokio::sync::OwnedSemaphorePermit, Error> {
+    let limit = &ctx.data().rpc_limit;
+    match tokio::time::timeout(limit.wait, limit.permits.clone().acquire_owned()).await {
+        Ok(permit) => Ok(permit?),
+        Err(_) => Err("The bot is busy right now, try again in a few seconds".into()),
+    }
+}
+
+/// Tells the caller why a check failed, which poise only logs, and leaves everything else to
+/// the default handler.
+pub async fn on_error(error: FrameworkError<'_, Data, Error>) {
+    match error {
+        FrameworkError::CommandCheckFailed {
+            error: Some(error),
+            ctx,
+            ..
+        } => {
+            let reply = poise::CreateReply::default()
+                .content(error.to_string())
+                .ephemeral(true);
+            if let Err(err) = ctx.send(reply).await {
+                eprintln!("on_error: {err}");
+            }
+        }
+        FrameworkError::CooldownHit {
+            remaining_cooldown,
+            ctx,
+            ..
+        } => {
+            let ready = SystemTime::now() + remaining_cooldown;
+            let ready = ready
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default();
+            let reply = poise::CreateReply::default()
+                .content(format!(
+                    "`/{}` is on cooldown, try again <t:{}:R>",
+                    ctx.command().qualified_name,
+                    ready.as_secs() + 1
+                ))
+                .ephemeral(true);
+            if let Err(err) = ctx.send(reply).await {
+                eprintln!("on_error: {err}");
+            }
+        }
+        error => {
+            if let Err(err) = poise::builtins::on_error(error).await {
+                eprintln!("on_error: {err}");
+            }
+        }
+    }
+}
@@ -0,0 +1,952 @@
+use crate::commands::lp::ASSETS;
+use crate::rpc::heads;
+use crate::tasks::arb_alerts::ArbAlertSettings;
+use crate::tasks::dashboards::DashboardSettings;
+use crate::tasks::presence::PresenceSettings;
+use crate::tasks::recorder::{RecorderSettings, QUOTE};
+use crate::tasks::swap_feed::SwapFeedSettings;
+use crate::tasks::validator_alerts::AlertSettings;
+use poise::serenity_prelude::{ChannelId, Colour};
+use rust_decimal::prelude::*;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::time::Duration;
+use toml::{Table, Value};
+
+const DEFAULT_CONFIG_PATH: &str = "jitcord.toml";
+
+/// Command groups that can be enabled with the `commands` key.
+pub const COMMAND_GROUPS: &[&str] = &[
+    "cf",
+    "lp",
+    "alerts",
+    "config",
+    "dashboard",
+    "digest",
+    "help",
+    "admin",
+];
+
+/// How an environment variable is turned into a config value.
+#[derive(Clone, Copy)]
+enum EnvKind {
+    /// Taken verbatim.
+    Text,
+    /// Comma separated, e.g. a list of endpoints.
+    List,
+    /// Parsed as a TOML literal, e.g. a number.
+    Literal,
+}
+
+/// Environment variables and the config keys they override.
+const ENV_OVERRIDES: &[(&str, &str, EnvKind)] = &[
+    ("JITCORD_DISCORD_TOKEN", "discord.token", EnvKind::Text),
+    ("JITCORD_TARGET", "rpc.endpoints", EnvKind::List),
+    ("JITCORD_WS_TARGET", "rpc.ws_endpoints", EnvKind::List),
+    ("JITCORD_ARCHIVE_TARGET", "rpc.archive", EnvKind::Text),
+    (
+        "JITCORD_HEALTH_INTERVAL_SECS",
+        "rpc.health_interval_secs",
+        EnvKind::Literal,
+    ),
+    ("JITCORD_NETWORK", "default_network", EnvKind::Text),
+    ("JITCORD_COMMANDS", "commands", EnvKind::List),
+    ("JITCORD_DB_PATH", "database.path", EnvKind::Text),
+    ("JITCORD_PREFIX", "prefix.default", EnvKind::Text),
+    ("JITCORD_REGISTRATION", "registration.mode", EnvKind::Text),
+    (
+        "JITCORD_REGISTRATION_GUILDS",
+        "registration.guilds",
+        EnvKind::Literal,
+    ),
+    ("JITCORD_OWNERS", "admin.owners", EnvKind::Literal),
+    (
+        "JITCORD_MESSAGE_CONTENT",
+        "prefix.message_content",
+        EnvKind::Literal,
+    ),
+    ("JITCORD_COLOUR_NEUTRAL", "colours.neutral", EnvKind::Text),
+    ("JITCORD_COLOUR_POSITIVE", "colours.positive", EnvKind::Text),
+    ("JITCORD_COLOUR_NEGATIVE", "colours.negative", EnvKind::Text),
+    ("JITCORD_COLOUR_WARNING", "colours.warning", EnvKind::Text),
+    (
+        "JITCORD_ALERT_EVERY_BLOCKS",
+        "alerts.every_blocks",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_ALERT_HEARTBEAT_LAG",
+        "alerts.max_heartbeat_lag",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_ALERT_MIN_REPUTATION",
+        "alerts.min_reputation",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_ALERT_AUCTION_WINDOW",
+        "alerts.auction_window",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_SWAP_FEED_CHANNEL",
+        "swap_feed.channel",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_SWAP_FEED_MIN_USD",
+        "swap_feed.min_usd",
+        EnvKind::Literal,
+    ),
+    ("JITCORD_PRESENCE", "presence.enabled", EnvKind::Literal),
+    ("JITCORD_PRESENCE_POOL", "presence.pool", EnvKind::Text),
+    (
+        "JITCORD_DASHBOARD_EVERY_BLOCKS",
+        "dashboards.every_blocks",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_SWAP_FEED_EVERY_BLOCKS",
+        "swap_feed.every_blocks",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_RECORDER_EVERY_BLOCKS",
+        "recorder.every_blocks",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_RECORDER_RAW_RETENTION_HOURS",
+        "recorder.raw_retention_hours",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_RECORDER_MINUTE_RETENTION_DAYS",
+        "recorder.minute_retention_days",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_RECORDER_HOUR_RETENTION_DAYS",
+        "recorder.hour_retention_days",
+        EnvKind::Literal,
+    ),
+    ("JITCORD_PRICE_FEED_URL", "price_feed.url", EnvKind::Text),
+    (
+        "JITCORD_PRICE_FEED_POINTER",
+        "price_feed.pointer",
+        EnvKind::Text,
+    ),
+    ("JITCORD_PRICE_FEED_FILE", "price_feed.file", EnvKind::Text),
+    (
+        "JITCORD_ARB_ALERT_CHANNEL",
+        "arb_alerts.channel",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_ARB_ALERT_BPS",
+        "arb_alerts.threshold_bps",
+        EnvKind::Literal,
+    ),
+    (
+        "JITCORD_ARB_ALERT_EVERY_BLOCKS",
+        "arb_alerts.every_blocks",
+        EnvKind::Literal,
+    ),
+];
+
+/// Embed colours, configured as `#rrggbb` strings.
+#[derive(Clone, Copy, Debug)]
+pub struct Colours {
+    pub neutral: Colour,
+    pub positive: Colour,
+    pub negative: Colour,
+    pub warning: Colour,
+}
+
+impl Default for Colours {
+    fn default() -> Self {
+        Colours {
+            neutral: Colour::DARK_GREY,
+            positive: Colour::DARK_GREEN,
+            negative: Colour::DARK_RED,
+            warning: Colour::ORANGE,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NetworkConfig {
+    pub name: String,
+    pub endpoints: Vec<String>,
+    pub ws_endpoints: Vec<String>,
+    /// Archive node used by `backfill`.
+    pub archive: Option<String>,
+    pub colour: Colour,
+    pub assets: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum PriceFeedConfig {
+    Http { url: String, pointer: String },
+    File { path: String },
+}
+
+/// Cooldowns of a command, unset when zero.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cooldown {
+    pub user: Option<Duration>,
+    pub global: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Cooldowns {
+    /// Applies to every command without its own entry.
+    pub default: Cooldown,
+    /// By qualified command name, e.g. `cf account_info`.
+    pub commands: Vec<(String, Cooldown)>,
+}
+
+/// Caps how many RPC heavy commands run at once.
+#[derive(Clone, Copy, Debug)]
+pub struct RpcLimitSettings {
+    pub max_concurrent: usize,
+    /// How long a command waits for a slot before giving up.
+    pub wait: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct PrefixSettings {
+    /// Prefix for servers that haven't set their own.
+    pub default: String,
+    /// Requests the privileged intent needed to read messages that don't mention the bot.
+    pub message_content: bool,
+    /// How long edited messages re-run their command, disabled when unset.
+    pub edit_tracking: Option<Duration>,
+}
+
+/// Whether a prefix is short and can be told apart from the command after it.
+pub fn valid_prefix(prefix: &str) -> bool {
+    (1..=5).contains(&prefix.chars().count()) && !prefix.chars().any(char::is_whitespace)
+}
+
+/// Where slash commands are registered on startup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegistrationMode {
+    Global,
+    /// Only in [`RegistrationSettings::guilds`], which shows changes instantly.
+    Guilds,
+    /// Left to `/admin register`.
+    Off,
+}
+
+#[derive(Clone, Debug)]
+pub struct RegistrationSettings {
+    pub mode: RegistrationMode,
+    pub guilds: Vec<u64>,
+    /// Command groups only registered in, and usable from, the listed guilds.
+    pub experimental: Vec<(String, Vec<u64>)>,
+}
+
+/// What the config is loaded for, which decides the required keys.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Bot,
+    Backfill,
+}
+
+/// Everything the bot reads at startup.
+pub struct Config {
+    pub discord_token: String,
+    pub networks: Vec<NetworkConfig>,
+    pub default_network: String,
+    pub health_interval: Duration,
+    pub colours: Colours,
+    pub db_path: String,
+    pub command_groups: Vec<String>,
+    pub alerts: AlertSettings,
+    pub dashboards: DashboardSettings,
+    pub presence: Option<PresenceSettings>,
+    pub swap_feed: Option<SwapFeedSettings>,
+    pub recorder: RecorderSettings,
+    pub price_feed: Option<PriceFeedConfig>,
+    pub arb_alerts: Option<ArbAlertSettings>,
+    pub prefix: PrefixSettings,
+    pub registration: RegistrationSettings,
+    /// Users allowed to run `/admin` besides the application's owners.
+    pub owners: Vec<u64>,
+    pub cooldowns: Cooldowns,
+    pub rpc_limit: RpcLimitSettings,
+}
+
+/// Every problem found while loading the config.
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid configuration:")?;
+        for error in &self.0 {
+            writeln!(f, "  - {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+impl Config {
+    /// Loads the file named by `JITCORD_CONFIG` (default `jitcord.toml`, which may be
+    /// absent), applies the selected profile and then the environment. The Discord token and
+    /// endpoints are only required in [`Mode::Bot`].
+    pub fn load(mode: Mode) -> Result<Config, ConfigErrors> {
+        let mut errors = Vec::new();
+        let table = read_file(&mut errors);
+        Config::resolve(table, errors, |name| std::env::var(name).ok(), mode)
+    }
+
+    /// Applies the profile and environment overrides to a parsed file. `env` looks up
+    /// environment variables, so tests don't depend on the process environment.
+    fn resolve(
+        mut table: Table,
+        mut errors: Vec<String>,
+        env: impl Fn(&str) -> Option<String>,
+        mode: Mode,
+    ) -> Result<Config, ConfigErrors> {
+        let profile =
+            env("JITCORD_PROFILE").or_else(|| table.get("profile")?.as_str().map(String::from));
+        let profiles = table.remove("profiles");
+        if let Some(profile) = profile {
+            match profiles.as_ref().and_then(|p| p.get(&profile)) {
+                Some(Value::Table(overlay)) => merge(&mut table, overlay.clone()),
+                Some(_) => errors.push(format!("profiles.{profile}: expected a table")),
+                None => errors.push(format!("profile {profile:?} is not defined")),
+            }
+        }
+        for (name, key, kind) in ENV_OVERRIDES {
+            if let Some(raw) = env(name) {
+                set(&mut table, key, env_value(&raw, *kind));
+            }
+        }
+        let mut reader = Reader { table, errors };
+        let config = Config::read(&mut reader, mode);
+        match reader.errors.is_empty() {
+            true => Ok(config),
+            false => Err(ConfigErrors(reader.errors)),
+        }
+    }
+
+    fn read(r: &mut Reader, mode: Mode) -> Config {
+        let discord_token: String = r.or("discord.token", String::new());
+        r.check(
+            mode != Mode::Bot || !discord_token.is_empty(),
+            "discord.token (JITCORD_DISCORD_TOKEN) is required",
+        );
+
+        let defaults = Colours::default();
+        let colours = Colours {
+            neutral: r.colour("colours.neutral", defaults.neutral),
+            positive: r.colour("colours.positive", defaults.positive),
+            negative: r.colour("colours.negative", defaults.negative),
+            warning: r.colour("colours.warning", defaults.warning),
+        };
+
+        // A single network can be configured through [rpc], several through [networks.<name>].
+        let default_network: String = r.or("default_network", "mainnet".to_string());
+        let networks = match lookup(&r.table, "networks").cloned() {
+            Some(Value::Table(networks)) => {
+                r.check(
+                    lookup(&r.table, "rpc.endpoints").is_none(),
+                    "rpc.endpoints can't be combined with [networks], move them into a network",
+                );
+                networks
+                    .keys()
+                    .map(|name| r.network(&format!("networks.{name}"), name, mode, colours))
+                    .collect()
+            }
+            Some(_) => {
+                r.check(false, "networks: expected a table of networks");
+                Vec::new()
+            }
+            None => vec![r.network("rpc", &default_network, mode, colours)],
+        };
+        r.check(
+            networks.iter().any(|n| n.name == default_network),
+            format!("default_network: {default_network:?} is not a configured network"),
+        );
+        let health_interval = Duration::from_secs(r.positive("rpc.health_interval_secs", 30));
+
+        let db_path: String = r.or("database.path", "jitcord.db".to_string());
+        r.check(!db_path.is_empty(), "database.path must not be empty");
+
+        let command_groups: Vec<String> = r.or(
+            "commands",
+            COMMAND_GROUPS.iter().map(|g| g.to_string()).collect(),
+        );
+        for group in &command_groups {
+            r.check(
+                COMMAND_GROUPS.contains(&group.as_str()),
+                format!(
+                    "commands: unknown group {group:?}, expected one of {}",
+                    COMMAND_GROUPS.join(", ")
+                ),
+            );
+        }
+
+        let mode = match r.or("registration.mode", "global".to_string()).as_str() {
+            "global" => RegistrationMode::Global,
+            "guilds" => RegistrationMode::Guilds,
+            "off" => RegistrationMode::Off,
+            mode => {
+                r.check(
+                    false,
+                    format!(
+                        "registration.mode: unknown mode {mode:?}, expected global, guilds or off"
+                    ),
+                );
+                RegistrationMode::Global
+            }
+        };
+        let registration = RegistrationSettings {
+            mode,
+            guilds: r.or("registration.guilds", Vec::new()),
+            experimental: match lookup(&r.table, "registration.experimental").cloned() {
+                Some(Value::Table(groups)) => groups
+                    .keys()
+                    .map(|group| {
+                        let guilds =
+                            r.or(&format!("registration.experimental.{group}"), Vec::new());
+                        (group.clone(), guilds)
+                    })
+                    .collect(),
+                Some(_) => {
+                    r.check(
+                        false,
+                        "registration.experimental: expected a table of groups",
+                    );
+                    Vec::new()
+                }
+                None => Vec::new(),
+            },
+        };
+        r.check(
+            mode != RegistrationMode::Guilds || !registration.guilds.is_empty(),
+            "registration.guilds must list at least one guild when registration.mode is guilds",
+        );
+        for (group, _) in &registration.experimental {
+            r.check(
+                command_groups.contains(group),
+                format!("registration.experimental: {group:?} is not an enabled command group"),
+            );
+        }
+        let owners = r.or("admin.owners", Vec::new());
+
+        let dashboards = DashboardSettings {
+            every_blocks: r.positive("dashboards.every_blocks", 10),
+        };
+
+        let presence = match r.or("presence.enabled", true) {
+            true => {
+                let pool = r
+                    .get::<String>("presence.pool")
+                    .map(|base| base.to_uppercase());
+                // The presence follows the default network, so its pool must exist there.
+                let default_assets = networks
+                    .iter()
+                    .find(|n| n.name == default_network)
+                    .map(|n| n.assets.clone())
+                    .unwrap_or_default();
+                if let Some(base) = &pool {
+                    r.check(
+                        default_assets.contains(base) && base != QUOTE,
+                        format!("presence.pool: {base} is not a base asset on {default_network}"),
+                    );
+                }
+                Some(PresenceSettings {
+                    every_blocks: r.positive("presence.every_blocks", 2),
+                    pool: pool.map(|base| (base, QUOTE.to_string())),
+                })
+            }
+            false => None,
+        };
+
+        let alerts = AlertSettings {
+            every_blocks: r.positive("alerts.every_blocks", 1),
+            max_heartbeat_lag: r.or("alerts.max_heartbeat_lag", 300),
+            min_reputation: r.or("alerts.min_reputation", 0),
+            auction_window: r.or("alerts.auction_window", 1200),
+            colours,
+        };
+
+        let swap_feed = r.channel("swap_feed.channel").map(|channel| {
+            let min_usd: f64 = r.or("swap_feed.min_usd", 50_000.0);
+            r.check(min_usd >= 0.0, "swap_feed.min_usd must not be negative");
+            SwapFeedSettings {
+                channel,
+                min_usd: Decimal::from_f64(min_usd).unwrap_or_default(),
+                every_blocks: r.positive("swap_feed.every_blocks", 1),
+                colours,
+            }
+        });
+
+        let recorder = RecorderSettings {
+            every_blocks: r.positive("recorder.every_blocks", 1),
+            raw_retention: r.duration("recorder.raw_retention_hours", 48, 3600),
+            minute_retention: r.duration("recorder.minute_retention_days", 30, 86400),
+            hour_retention: r.duration("recorder.hour_retention_days", 365, 86400),
+        };
+
+        let price_feed = match (
+            r.get::<String>("price_feed.url"),
+            r.get::<String>("price_feed.file"),
+        ) {
+            (Some(_), Some(_)) => {
+                r.check(false, "price_feed: set either url or file, not both");
+                None
+            }
+            (Some(url), None) => Some(PriceFeedConfig::Http {
+                url,
+                pointer: r.or("price_feed.pointer", "/price".to_string()),
+            }),
+            (None, Some(path)) => Some(PriceFeedConfig::File { path }),
+            (None, None) => None,
+        };
+
+        let arb_alerts = r.channel("arb_alerts.channel").map(|channel| {
+            let threshold_bps: f64 = r.or("arb_alerts.threshold_bps", 50.0);
+            r.check(
+                threshold_bps > 0.0,
+                "arb_alerts.threshold_bps must be positive",
+            );
+            ArbAlertSettings {
+                channel,
+                threshold_bps,
+                every_blocks: r.positive("arb_alerts.every_blocks", 10),
+                colours,
+            }
+        });
+        r.check(
+            arb_alerts.is_none() || price_feed.is_some(),
+            "arb_alerts needs a price_feed to compare against",
+        );
+
+        let prefix = PrefixSettings {
+            default: r.or("prefix.default", "!".to_string()),
+            message_content: r.or("prefix.message_content", false),
+            edit_tracking: Some(r.or("prefix.edit_tracking_secs", 300))
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        };
+        r.check(
+            valid_prefix(&prefix.default),
+            format!(
+                "prefix.default: {:?} must be 1 to 5 characters without spaces",
+                prefix.default
+            ),
+        );
+
+        let cooldowns = Cooldowns {
+            default: r.cooldown("cooldowns"),
+            commands: match lookup(&r.table, "cooldowns.commands").cloned() {
+                Some(Value::Table(commands)) => commands
+                    .keys()
+                    .map(|name| {
+                        let cooldown = r.cooldown(&format!("cooldowns.commands.{name}"));
+                        (name.clone(), cooldown)
+                    })
+                    .collect(),
+                Some(_) => {
+                    r.check(false, "cooldowns.commands: expected a table of commands");
+                    Vec::new()
+                }
+                None => Vec::new(),
+            },
+        };
+
+        let rpc_limit = RpcLimitSettings {
+            max_concurrent: r.positive("limits.max_concurrent_rpc_commands", 4),
+            wait: Duration::from_secs(r.or("limits.rpc_command_wait_secs", 10)),
+        };
+
+        Config {
+            discord_token,
+            networks,
+            default_network,
+            health_interval,
+            colours,
+            db_path,
+            command_groups,
+            alerts,
+            dashboards,
+            presence,
+            swap_feed,
+            recorder,
+            price_feed,
+            arb_alerts,
+            prefix,
+            registration,
+            owners,
+            cooldowns,
+            rpc_limit,
+        }
+    }
+}
+
+/// Reads typed values out of the merged table, collecting errors instead of stopping at
+/// the first one.
+struct Reader {
+    table: Table,
+    errors: Vec<String>,
+}
+
+impl Reader {
+    fn get<T: DeserializeOwned>(&mut self, key: &str) -> Option<T> {
+        let value = lookup(&self.table, key)?.clone();
+        match T::deserialize(value) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.errors.push(format!("{key}: {}", err.message().trim()));
+                None
+            }
+        }
+    }
+
+    fn or<T: DeserializeOwned>(&mut self, key: &str, default: T) -> T {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// A count or interval that must be at least 1.
+    fn positive<T>(&mut self, key: &str, default: T) -> T
+    where
+        T: DeserializeOwned + PartialOrd + From<u8>,
+    {
+        let value = self.or(key, default);
+        self.check(value >= T::from(1), format!("{key} must be at least 1"));
+        value
+    }
+
+    /// A retention given in whole `unit`s of seconds, e.g. hours.
+    fn duration(&mut self, key: &str, default: u64, unit: u64) -> Duration {
+        let value = self.or(key, default);
+        match value.checked_mul(unit) {
+            Some(secs) => Duration::from_secs(secs),
+            None => {
+                self.errors.push(format!("{key}: {value} is too large"));
+                Duration::from_secs(default * unit)
+            }
+        }
+    }
+
+    fn network(&mut self, prefix: &str, name: &str, mode: Mode, colours: Colours) -> NetworkConfig {
+        self.check(
+            name.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+            format!("{prefix}: network names may only contain letters, digits, - and _"),
+        );
+        let endpoints: Vec<String> = self.or(&format!("{prefix}.endpoints"), Vec::new());
+        self.check(
+            mode != Mode::Bot || !endpoints.is_empty(),
+            format!("{prefix}.endpoints needs at least one endpoint"),
+        );
+        for endpoint in &endpoints {
+            self.check(
+                endpoint.starts_with("http://") || endpoint.starts_with("https://"),
+                format!("{prefix}.endpoints: {endpoint:?} is not an http(s) URL"),
+            );
+        }
+        // Derived endpoints are only as valid as the HTTP ones, so only explicit ones are checked.
+        let ws_endpoints = match self.get::<Vec<String>>(&format!("{prefix}.ws_endpoints")) {
+            Some(ws_endpoints) => {
+                for endpoint in &ws_endpoints {
+                    self.check(
+                        endpoint.starts_with("ws://") || endpoint.starts_with("wss://"),
+                        format!("{prefix}.ws_endpoints: {endpoint:?} is not a ws(s) URL"),
+                    );
+                }
+                ws_endpoints
+            }
+            None => endpoints.iter().map(|e| heads::ws_url(e)).collect(),
+        };
+        let assets: Vec<String> = self.or(
+            &format!("{prefix}.assets"),
+            ASSETS.iter().map(|a| a.to_string()).collect(),
+        );
+        for asset in &assets {
+            self.check(
+                ASSETS.contains(&asset.as_str()),
+                format!("{prefix}.assets: {asset} is not a supported asset"),
+            );
+        }
+        NetworkConfig {
+            name: name.to_string(),
+            endpoints,
+            ws_endpoints,
+            archive: self.get(&format!("{prefix}.archive")),
+            colour: self.colour(&format!("{prefix}.colour"), colours.neutral),
+            assets,
+        }
+    }
+
+    fn cooldown(&mut self, prefix: &str) -> Cooldown {
+        let mut seconds = |key: &str| {
+            self.get::<u64>(&format!("{prefix}.{key}"))
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+        };
+        Cooldown {
+            user: seconds("user_secs"),
+            global: seconds("global_secs"),
+        }
+    }
+
+    /// An optional channel, where 0 means disabled.
+    fn channel(&mut self, key: &str) -> Option<ChannelId> {
+        self.get::<u64>(key)
+            .filter(|id| *id != 0)
+            .map(ChannelId::new)
+    }
+
+    fn colour(&mut self, key: &str, default: Colour) -> Colour {
+        let Some(hex) = self.get::<String>(key) else {
+            return default;
+        };
+        match u32::from_str_radix(hex.trim_start_matches('#'), 16) {
+            Ok(value) if value <= 0xFFFFFF => Colour::new(value),
+            _ => {
+                self.errors
+                    .push(format!("{key}: {hex:?} is not a #rrggbb colour"));
+                default
+            }
+        }
+    }
+
+    fn check(&mut self, ok: bool, error: impl Into<String>) {
+        if !ok {
+            self.errors.push(error.into());
+        }
+    }
+}
+
+fn read_file(errors: &mut Vec<String>) -> Table {
+    let (path, required) = match std::env::var("JITCORD_CONFIG") {
+        Ok(path) => (path, true),
+        Err(_) => (DEFAULT_CONFIG_PATH.to_string(), false),
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => return Table::new(),
+        Err(err) => {
+            errors.push(format!("{path}: {err}"));
+            return Table::new();
+        }
+    };
+    contents.parse::<Table>().unwrap_or_else(|err| {
+        errors.push(format!("{path}: {}", err.message().trim()));
+        Table::new()
+    })
+}
+
+fn env_value(raw: &str, kind: EnvKind) -> Value {
+    match kind {
+        EnvKind::Text => Value::String(raw.to_string()),
+        EnvKind::List => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        ),
+        EnvKind::Literal => format!("value = {raw}")
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| Value::String(raw.to_string())),
+    }
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let (first, rest) = key.split_once('.').unwrap_or((key, ""));
+    let value = table.get(first)?;
+    match rest {
+        "" => Some(value),
+        rest => lookup(value.as_table()?, rest),
+    }
+}
+
+fn set(table: &mut Table, key: &str, value: Value) {
+    match key.split_once('.') {
+        None => {
+            table.insert(key.to_string(), value);
+        }
+        Some((first, rest)) => {
+            let child = table
+                .entry(first)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if !child.is_table() {
+                *child = Value::Table(Table::new());
+            }
+            if let Value::Table(child) = child {
+                set(child, rest, value);
+            }
+        }
+    }
+}
+
+/// Overlays `overlay` onto `base`, merging nested tables key by key.
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn resolve(file: &str, env: &[(&str, &str)], mode: Mode) -> Result<Config, ConfigErrors> {
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let table = file.parse::<Table>().unwrap();
+        Config::resolve(table, Vec::new(), |name| env.get(name).cloned(), mode)
+    }
+
+    const FILE: &str = r#"
+        profile = "mainnet"
+
+        [discord]
+        token = "file-token"
+
+        [rpc]
+        endpoints = ["http://localhost:9944"]
+        health_interval_secs = 15
+
+        [recorder]
+        every_blocks = 2
+
+        [profiles.mainnet.rpc]
+        endpoints = ["https://mainnet.example"]
+
+        [profiles.mainnet.recorder]
+        raw_retention_hours = 12
+
+        [profiles.testnet.rpc]
+        endpoints = ["https://testnet.example"]
+    "#;
+
+    #[test]
+    fn profile_merges_nested_tables() {
+        let config = resolve(FILE, &[], Mode::Bot).unwrap();
+        assert_eq!(config.networks[0].endpoints, ["https://mainnet.example"]);
+        // Keys the profile doesn't mention are kept from the base table.
+        assert_eq!(config.health_interval, Duration::from_secs(15));
+        assert_eq!(config.recorder.every_blocks, 2);
+        assert_eq!(
+            config.recorder.raw_retention,
+            Duration::from_secs(12 * 3600)
+        );
+        assert_eq!(config.networks[0].ws_endpoints, ["wss://mainnet.example"]);
+    }
+
+    #[test]
+    fn profile_env_overrides_file_selection() {
+        let config = resolve(FILE, &[("JITCORD_PROFILE", "testnet")], Mode::Bot).unwrap();
+        assert_eq!(config.networks[0].endpoints, ["https://testnet.example"]);
+        assert_eq!(
+            config.recorder.raw_retention,
+            Duration::from_secs(48 * 3600)
+        );
+    }
+
+    #[test]
+    fn env_overrides_file_and_profile() {
+        let config = resolve(
+            FILE,
+            &[
+                ("JITCORD_DISCORD_TOKEN", "env-token"),
+                ("JITCORD_TARGET", "http://a.example, http://b.example"),
+                ("JITCORD_RECORDER_RAW_RETENTION_HOURS", "6"),
+            ],
+            Mode::Bot,
+        )
+        .unwrap();
+        assert_eq!(config.discord_token, "env-token");
+        assert_eq!(
+            config.networks[0].endpoints,
+            ["http://a.example", "http://b.example"]
+        );
+        assert_eq!(config.recorder.raw_retention, Duration::from_secs(6 * 3600));
+    }
+
+    #[test]
+    fn env_values_by_kind() {
+        let cases = [
+            ("text", EnvKind::Text, Value::String("text".into())),
+            ("42", EnvKind::Literal, Value::Integer(42)),
+            ("2.5", EnvKind::Literal, Value::Float(2.5)),
+            (
+                "not toml",
+                EnvKind::Literal,
+                Value::String("not toml".into()),
+            ),
+            (
+                "a,,b ",
+                EnvKind::List,
+                Value::Array(vec![Value::String("a".into()), Value::String("b".into())]),
+            ),
+        ];
+        for (raw, kind, expected) in cases {
+            assert_eq!(env_value(raw, kind), expected, "{raw:?}");
+        }
+    }
+
+    #[test]
+    fn errors_are_collected() {
+        let file = r##"
+            profile = "missing"
+            commands = ["cf", "nope"]
+
+            [rpc]
+            endpoints = ["localhost:9944"]
+            health_interval_secs = 0
+
+            [colours]
+            neutral = "#12345g"
+
+            [recorder]
+            hour_retention_days = 9223372036854775807
+        "##;
+        let errors = match resolve(file, &[], Mode::Bot) {
+            Ok(_) => panic!("config should be rejected"),
+            Err(ConfigErrors(errors)) => errors,
+        };
+        let unknown_group = format!(
+            "commands: unknown group \"nope\", expected one of {}",
+            COMMAND_GROUPS.join(", ")
+        );
+        let expected = [
+            "profile \"missing\" is not defined",
+            "discord.token (JITCORD_DISCORD_TOKEN) is required",
+            "colours.neutral: \"#12345g\" is not a #rrggbb colour",
+            "rpc.endpoints: \"localhost:9944\" is not an http(s) URL",
+            "rpc.health_interval_secs must be at least 1",
+            unknown_group.as_str(),
+            "recorder.hour_retention_days: 9223372036854775807 is too large",
+        ];
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn backfill_needs_no_token_or_endpoints() {
+        assert!(resolve("", &[], Mode::Backfill).is_ok());
+        let errors = resolve("", &[], Mode::Bot).err().unwrap().0;
+        assert_eq!(errors.len(), 2);
+    }
+}
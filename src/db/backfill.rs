@@ -0,0 +1,34 @@
+use super::Db;
+use crate::Error;
+use rusqlite::{params, OptionalExtension};
+
+impl Db {
+    /// The next block to backfill for a run identified by `range`, if it was interrupted.
+    pub fn backfill_checkpoint(&self, range: &str) -> Result<Option<u32>, Error> {
+        self.with(|conn| {
+            conn.query_row(
+                "SELECT next_block FROM backfill_checkpoint WHERE range = ?1",
+                [range],
+                |row| row.get(0),
+            )
+            .optional()
+        })
+    }
+
+    pub fn set_backfill_checkpoint(&self, range: &str, next_block: u32) -> Result<(), Error> {
+        self.with(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO backfill_checkpoint VALUES (?1, ?2)",
+                params![range, next_block],
+            )
+        })?;
+        Ok(())
+    }
+
+    pub fn clear_backfill_checkpoint(&self, range: &str) -> Result<(), Error> {
+        self.with(|conn| {
+            conn.execute("DELETE FROM backfill_checkpoint WHERE range = ?1", [range])
+        })?;
+        Ok(())
+    }
+}
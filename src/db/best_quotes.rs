@@ -0,0 +1,103 @@
+use super::snapshots::RAW;
+use super::Db;
+use crate::Error;
+use rusqlite::params;
+use std::collections::BTreeMap;
+
+/// How many recorded snapshots an LP held the best bid and the best ask in.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BestQuoteCounts {
+    pub bid: u32,
+    pub ask: u32,
+}
+
+impl Db {
+    /// Records the LPs holding the best bid and ask in the snapshot at `timestamp`, replacing
+    /// any recorded for it before so replaying a block doesn't count it twice.
+    pub fn set_best_quote_holders(
+        &self,
+        base: &str,
+        quote: &str,
+        timestamp: i64,
+        bid_holders: &[&str],
+        ask_holders: &[&str],
+    ) -> Result<(), Error> {
+        self.with(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "DELETE FROM best_quote
+                WHERE base = ?1 AND quote = ?2 AND resolution = ?3 AND timestamp = ?4",
+                params![base, quote, RAW, timestamp],
+            )?;
+            let mut statement =
+                tx.prepare("INSERT INTO best_quote VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1)")?;
+            for (side, holders) in [("bid", bid_holders), ("ask", ask_holders)] {
+                for lp in holders {
+                    statement.execute(params![base, quote, RAW, timestamp, lp, side])?;
+                }
+            }
+            drop(statement);
+            tx.commit()
+        })
+    }
+
+    /// Best bid and ask counts per LP in the buckets of any resolution starting at or after
+    /// `from`.
+    pub fn best_quote_counts_since(
+        &self,
+        base: &str,
+        quote: &str,
+        from: i64,
+    ) -> Result<BTreeMap<String, BestQuoteCounts>, Error> {
+        self.with(|conn| {
+            let mut counts: BTreeMap<String, BestQuoteCounts> = BTreeMap::new();
+            let mut statement = conn.prepare(
+                "SELECT lp, side, SUM(count) FROM best_quote
+                WHERE base = ?1 AND quote = ?2 AND timestamp >= ?3
+                GROUP BY lp, side",
+            )?;
+            let mut rows = statement.query(params![base, quote, from])?;
+            while let Some(row) = rows.next()? {
+                let entry = counts.entry(row.get(0)?).or_default();
+                match row.get::<_, String>(1)?.as_str() {
+                    "bid" => entry.bid = row.get(2)?,
+                    _ => entry.ask = row.get(2)?,
+                }
+            }
+            Ok(counts)
+        })
+    }
+
+    /// Sums counts at resolution `from` older than `before` into buckets of `to` seconds, like
+    /// [`Db::compact_pool_snapshots`].
+    pub fn compact_best_quotes(&self, from: i64, to: i64, before: i64) -> Result<usize, Error> {
+        self.with(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "INSERT OR REPLACE INTO best_quote
+                SELECT base, quote, ?2, bucket, lp, side, SUM(count)
+                FROM (
+                    SELECT *, timestamp - timestamp % ?2 AS bucket FROM best_quote
+                    WHERE resolution = ?1 AND timestamp < ?3
+                )
+                GROUP BY base, quote, bucket, lp, side",
+                params![from, to, before],
+            )?;
+            let compacted = tx.execute(
+                "DELETE FROM best_quote WHERE resolution = ?1 AND timestamp < ?2",
+                params![from, before],
+            )?;
+            tx.commit()?;
+            Ok(compacted)
+        })
+    }
+
+    pub fn delete_best_quotes(&self, resolution: i64, before: i64) -> Result<usize, Error> {
+        self.with(|conn| {
+            conn.execute(
+                "DELETE FROM best_quote WHERE resolution = ?1 AND timestamp < ?2",
+                params![resolution, before],
+            )
+        })
+    }
+}
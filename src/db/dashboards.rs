@@ -0,0 +1,88 @@
+use super::Db;
+use crate::Error;
+use rusqlite::{params, Row};
+
+/// A message that is edited every few blocks to show the latest data.
+#[derive(Clone, Debug)]
+pub struct Dashboard {
+    pub id: i64,
+    pub guild_id: u64,
+    pub channel_id: u64,
+    pub message_id: u64,
+    /// What the message shows, e.g. `status`.
+    pub kind: String,
+    pub network: String,
+    /// The pool of pool dashboards.
+    pub base: Option<String>,
+    pub quote: Option<String>,
+}
+
+impl Dashboard {
+    fn from_row(row: &Row) -> rusqlite::Result<Dashboard> {
+        Ok(Dashboard {
+            id: row.get(0)?,
+            guild_id: row.get::<_, i64>(1)? as u64,
+            channel_id: row.get::<_, i64>(2)? as u64,
+            message_id: row.get::<_, i64>(3)? as u64,
+            kind: row.get(4)?,
+            network: row.get(5)?,
+            base: row.get(6)?,
+            quote: row.get(7)?,
+        })
+    }
+}
+
+const SELECT_DASHBOARD: &str =
+    "SELECT id, guild_id, channel_id, message_id, kind, network, base, quote FROM dashboard";
+
+impl Db {
+    /// Stores a dashboard, ignoring its `id`.
+    pub fn insert_dashboard(&self, dashboard: &Dashboard) -> Result<(), Error> {
+        self.with(|conn| {
+            conn.execute(
+                "INSERT INTO dashboard
+                    (guild_id, channel_id, message_id, kind, network, base, quote)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    dashboard.guild_id as i64,
+                    dashboard.channel_id as i64,
+                    dashboard.message_id as i64,
+                    dashboard.kind,
+                    dashboard.network,
+                    dashboard.base,
+                    dashboard.quote
+                ],
+            )
+        })?;
+        Ok(())
+    }
+
+    pub fn dashboards(&self, network: &str) -> Result<Vec<Dashboard>, Error> {
+        self.with(|conn| {
+            conn.prepare(&format!("{SELECT_DASHBOARD} WHERE network = ?1"))?
+                .query_map([network], Dashboard::from_row)?
+                .collect()
+        })
+    }
+
+    pub fn guild_dashboards(&self, guild_id: u64) -> Result<Vec<Dashboard>, Error> {
+        self.with(|conn| {
+            conn.prepare(&format!(
+                "{SELECT_DASHBOARD} WHERE guild_id = ?1 ORDER BY id"
+            ))?
+            .query_map([guild_id as i64], Dashboard::from_row)?
+            .collect()
+        })
+    }
+
+    /// Returns whether a dashboard was removed.
+    pub fn remove_dashboard(&self, guild_id: u64, message_id: u64) -> Result<bool, Error> {
+        let removed = self.with(|conn| {
+            conn.execute(
+                "DELETE FROM dashboard WHERE guild_id = ?1 AND message_id = ?2",
+                params![guild_id as i64, message_id as i64],
+            )
+        })?;
+        Ok(removed > 0)
+    }
+}
@@ -0,0 +1,114 @@
+use super::Db;
+use crate::Error;
+use rusqlite::{params, Row};
+use std::collections::BTreeSet;
+
+/// A report posted to a channel on a cron schedule.
+#[derive(Clone, Debug)]
+pub struct Digest {
+    pub id: i64,
+    pub guild_id: u64,
+    pub channel_id: u64,
+    /// What the report covers, e.g. `daily`.
+    pub period: String,
+    /// Cron expression, evaluated in the guild's timezone.
+    pub schedule: String,
+    pub network: String,
+    /// Unix timestamp of the last report posted.
+    pub last_sent: Option<i64>,
+    /// The authority set as of the last report, to list validators that joined or left.
+    pub authorities: Option<BTreeSet<String>>,
+}
+
+impl Digest {
+    fn from_row(row: &Row) -> rusqlite::Result<Digest> {
+        Ok(Digest {
+            id: row.get(0)?,
+            guild_id: row.get::<_, i64>(1)? as u64,
+            channel_id: row.get::<_, i64>(2)? as u64,
+            period: row.get(3)?,
+            schedule: row.get(4)?,
+            network: row.get(5)?,
+            last_sent: row.get(6)?,
+            authorities: row
+                .get::<_, Option<String>>(7)?
+                .map(|authorities| authorities.split_whitespace().map(String::from).collect()),
+        })
+    }
+}
+
+const SELECT_DIGEST: &str =
+    "SELECT id, guild_id, channel_id, period, schedule, network, last_sent, authorities
+    FROM digest";
+
+impl Db {
+    /// Stores a digest, ignoring its `id`, `last_sent` and `authorities`.
+    pub fn insert_digest(&self, digest: &Digest) -> Result<i64, Error> {
+        self.with(|conn| {
+            conn.execute(
+                "INSERT INTO digest (guild_id, channel_id, period, schedule, network)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    digest.guild_id as i64,
+                    digest.channel_id as i64,
+                    digest.period,
+                    digest.schedule,
+                    digest.network
+                ],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+    }
+
+    pub fn digests(&self) -> Result<Vec<Digest>, Error> {
+        self.with(|conn| {
+            conn.prepare(SELECT_DIGEST)?
+                .query_map([], Digest::from_row)?
+                .collect()
+        })
+    }
+
+    pub fn guild_digests(&self, guild_id: u64) -> Result<Vec<Digest>, Error> {
+        self.with(|conn| {
+            conn.prepare(&format!("{SELECT_DIGEST} WHERE guild_id = ?1 ORDER BY id"))?
+                .query_map([guild_id as i64], Digest::from_row)?
+                .collect()
+        })
+    }
+
+    /// Records a posted report along with the authority set the next one is compared against,
+    /// keeping the stored set when the report has none.
+    pub fn mark_digest_sent(
+        &self,
+        id: i64,
+        sent: i64,
+        authorities: Option<&BTreeSet<String>>,
+    ) -> Result<(), Error> {
+        let authorities = authorities.map(|authorities| {
+            authorities
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        self.with(|conn| {
+            conn.execute(
+                "UPDATE digest SET last_sent = ?2, authorities = COALESCE(?3, authorities)
+                WHERE id = ?1",
+                params![id, sent, authorities],
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Returns whether a digest was removed.
+    pub fn remove_digest(&self, guild_id: u64, id: i64) -> Result<bool, Error> {
+        let removed = self.with(|conn| {
+            conn.execute(
+                "DELETE FROM digest WHERE guild_id = ?1 AND id = ?2",
+                params![guild_id as i64, id],
+            )
+        })?;
+        Ok(removed > 0)
+    }
+}
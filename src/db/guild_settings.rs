@@ -0,0 +1,113 @@
+use super::Db;
+use crate::Error;
+use rusqlite::{params, OptionalExtension};
+use std::collections::BTreeMap;
+
+/// Per-guild overrides, where `None` falls back to the bot's configuration.
+#[derive(Clone, Debug, Default)]
+pub struct GuildSettings {
+    pub network: Option<String>,
+    /// Whether command replies are only visible to the caller.
+    pub ephemeral: bool,
+    /// Channels commands may be used in, any channel when empty.
+    pub allowed_channels: Vec<u64>,
+    pub alert_channel: Option<u64>,
+    /// IANA name, e.g. `Europe/Berlin`.
+    pub timezone: Option<String>,
+    /// Prefix for prefix commands.
+    pub prefix: Option<String>,
+    /// Role required to use a command group, by group name.
+    pub roles: BTreeMap<String, u64>,
+}
+
+impl Db {
+    /// Returns the defaults for guilds without stored settings.
+    pub fn guild_settings(&self, guild_id: u64) -> Result<GuildSettings, Error> {
+        self.with(|conn| {
+            let settings = conn
+                .query_row(
+                    "SELECT network, ephemeral, alert_channel, timezone, prefix
+                    FROM guild_settings WHERE guild_id = ?1",
+                    [guild_id as i64],
+                    |row| {
+                        Ok(GuildSettings {
+                            network: row.get(0)?,
+                            ephemeral: row.get(1)?,
+                            allowed_channels: Vec::new(),
+                            alert_channel: row.get::<_, Option<i64>>(2)?.map(|id| id as u64),
+                            timezone: row.get(3)?,
+                            prefix: row.get(4)?,
+                            roles: BTreeMap::new(),
+                        })
+                    },
+                )
+                .optional()?;
+            let mut settings = settings.unwrap_or_default();
+            settings.allowed_channels = conn
+                .prepare("SELECT channel_id FROM guild_allowed_channel WHERE guild_id = ?1")?
+                .query_map([guild_id as i64], |row| Ok(row.get::<_, i64>(0)? as u64))?
+                .collect::<rusqlite::Result<_>>()?;
+            settings.roles = conn
+                .prepare(
+                    "SELECT command_group, role_id FROM guild_command_role WHERE guild_id = ?1",
+                )?
+                .query_map([guild_id as i64], |row| {
+                    Ok((row.get(0)?, row.get::<_, i64>(1)? as u64))
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+            Ok(settings)
+        })
+    }
+
+    pub fn save_guild_settings(
+        &self,
+        guild_id: u64,
+        settings: &GuildSettings,
+    ) -> Result<(), Error> {
+        self.with(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "INSERT INTO guild_settings
+                    (guild_id, network, ephemeral, alert_channel, timezone, prefix)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT (guild_id) DO UPDATE SET
+                    network = excluded.network,
+                    ephemeral = excluded.ephemeral,
+                    alert_channel = excluded.alert_channel,
+                    timezone = excluded.timezone,
+                    prefix = excluded.prefix",
+                params![
+                    guild_id as i64,
+                    settings.network,
+                    settings.ephemeral,
+                    settings.alert_channel.map(|id| id as i64),
+                    settings.timezone,
+                    settings.prefix
+                ],
+            )?;
+            tx.execute(
+                "DELETE FROM guild_allowed_channel WHERE guild_id = ?1",
+                [guild_id as i64],
+            )?;
+            for channel_id in &settings.allowed_channels {
+                tx.execute(
+                    "INSERT INTO guild_allowed_channel (guild_id, channel_id) VALUES (?1, ?2)",
+                    params![guild_id as i64, *channel_id as i64],
+                )?;
+            }
+            tx.execute(
+                "DELETE FROM guild_command_role WHERE guild_id = ?1",
+                [guild_id as i64],
+            )?;
+            for (group, role_id) in &settings.roles {
+                tx.execute(
+                    "INSERT INTO guild_command_role (guild_id, command_group, role_id)
+                    VALUES (?1, ?2, ?3)",
+                    params![guild_id as i64, group, *role_id as i64],
+                )?;
+            }
+            tx.commit()
+        })?;
+        Ok(())
+    }
+}
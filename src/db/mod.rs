@@ -0,0 +1,147 @@
+pub mod backfill;
+pub mod best_quotes;
+pub mod dashboards;
+pub mod digests;
+pub mod guild_settings;
+pub mod snapshots;
+pub mod volume;
+pub mod watchlist;
+
+use crate::Error;
+use rusqlite::Connection;
+use std::sync::{Arc, Mutex};
+
+// Each entry is applied once, in order, and tracked through `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE validator_watch (
+        id INTEGER PRIMARY KEY,
+        user_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        account TEXT NOT NULL,
+        min_reputation INTEGER,
+        max_heartbeat_lag INTEGER,
+        network TEXT NOT NULL,
+        UNIQUE (user_id, account, network)
+    );",
+    "CREATE TABLE pool_snapshot (
+        base TEXT NOT NULL,
+        quote TEXT NOT NULL,
+        resolution INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        block INTEGER NOT NULL,
+        open REAL NOT NULL,
+        high REAL NOT NULL,
+        low REAL NOT NULL,
+        close REAL NOT NULL,
+        best_bid REAL,
+        best_ask REAL,
+        bid_depth_1pct REAL NOT NULL,
+        ask_depth_1pct REAL NOT NULL,
+        bid_depth_5pct REAL NOT NULL,
+        ask_depth_5pct REAL NOT NULL,
+        range_liquidity REAL NOT NULL,
+        samples INTEGER NOT NULL,
+        PRIMARY KEY (base, quote, resolution, timestamp)
+    );",
+    "CREATE TABLE auction_snapshot (
+        block INTEGER PRIMARY KEY,
+        timestamp INTEGER NOT NULL,
+        epoch_started_at INTEGER NOT NULL,
+        blocks_per_epoch INTEGER NOT NULL,
+        min_active_bid REAL NOT NULL
+    );
+    CREATE TABLE backfill_checkpoint (
+        range TEXT PRIMARY KEY,
+        next_block INTEGER NOT NULL
+    );",
+    "CREATE TABLE pool_fill (
+        base TEXT NOT NULL,
+        quote TEXT NOT NULL,
+        resolution INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        volume REAL NOT NULL,
+        limit_fees REAL NOT NULL,
+        range_fees REAL NOT NULL,
+        PRIMARY KEY (base, quote, resolution, timestamp)
+    );",
+    "CREATE TABLE best_quote (
+        base TEXT NOT NULL,
+        quote TEXT NOT NULL,
+        resolution INTEGER NOT NULL,
+        timestamp INTEGER NOT NULL,
+        lp TEXT NOT NULL,
+        side TEXT NOT NULL,
+        count INTEGER NOT NULL,
+        PRIMARY KEY (base, quote, resolution, timestamp, lp, side)
+    );",
+    "CREATE TABLE guild_settings (
+        guild_id INTEGER PRIMARY KEY,
+        network TEXT
+    );",
+    "ALTER TABLE guild_settings ADD COLUMN ephemeral INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE guild_settings ADD COLUMN alert_channel INTEGER;
+    ALTER TABLE guild_settings ADD COLUMN timezone TEXT;
+    CREATE TABLE guild_allowed_channel (
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, channel_id)
+    );",
+    "CREATE TABLE guild_command_role (
+        guild_id INTEGER NOT NULL,
+        command_group TEXT NOT NULL,
+        role_id INTEGER NOT NULL,
+        PRIMARY KEY (guild_id, command_group)
+    );",
+    "ALTER TABLE guild_settings ADD COLUMN prefix TEXT;",
+    "CREATE TABLE dashboard (
+        id INTEGER PRIMARY KEY,
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        message_id INTEGER NOT NULL UNIQUE,
+        kind TEXT NOT NULL,
+        network TEXT NOT NULL,
+        base TEXT,
+        quote TEXT
+    );",
+    "CREATE TABLE digest (
+        id INTEGER PRIMARY KEY,
+        guild_id INTEGER NOT NULL,
+        channel_id INTEGER NOT NULL,
+        period TEXT NOT NULL,
+        schedule TEXT NOT NULL,
+        network TEXT NOT NULL,
+        last_sent INTEGER,
+        authorities TEXT
+    );",
+];
+
+#[derive(Clone, Debug)]
+pub struct Db {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Db {
+    pub fn open(path: &str) -> Result<Db, Error> {
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+        Ok(Db {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    pub fn with<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, Error> {
+        let conn = self.conn.lock().map_err(|_| "database lock poisoned")?;
+        Ok(f(&conn)?)
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), Error> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,319 @@
+use super::Db;
+use crate::analytics::book::BookSummary;
+use crate::Error;
+use rusqlite::{params, OptionalExtension, Row};
+
+/// Resolutions (in seconds) snapshots are stored at. Raw snapshots are taken per block.
+pub const RAW: i64 = 0;
+pub const MINUTE: i64 = 60;
+pub const HOUR: i64 = 3600;
+
+/// A pool snapshot, or an aggregate of `samples` snapshots when downsampled. The mid price
+/// is kept as open/high/low/close, everything else is averaged.
+#[derive(Clone, Debug)]
+pub struct PoolSnapshot {
+    pub base: String,
+    pub quote: String,
+    pub resolution: i64,
+    pub timestamp: i64,
+    pub block: u32,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+    pub bid_depth_1pct: f64,
+    pub ask_depth_1pct: f64,
+    pub bid_depth_5pct: f64,
+    pub ask_depth_5pct: f64,
+    pub range_liquidity: f64,
+    pub samples: u32,
+}
+
+impl PoolSnapshot {
+    pub fn new(base: &str, quote: &str, timestamp: i64, block: u32, book: &BookSummary) -> Self {
+        PoolSnapshot {
+            base: base.to_string(),
+            quote: quote.to_string(),
+            resolution: RAW,
+            timestamp,
+            block,
+            open: book.mid,
+            high: book.mid,
+            low: book.mid,
+            close: book.mid,
+            best_bid: book.best_bid,
+            best_ask: book.best_ask,
+            bid_depth_1pct: book.bid_depth_1pct,
+            ask_depth_1pct: book.ask_depth_1pct,
+            bid_depth_5pct: book.bid_depth_5pct,
+            ask_depth_5pct: book.ask_depth_5pct,
+            range_liquidity: book.range_liquidity,
+            samples: 1,
+        }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<PoolSnapshot> {
+        Ok(PoolSnapshot {
+            base: row.get(0)?,
+            quote: row.get(1)?,
+            resolution: row.get(2)?,
+            timestamp: row.get(3)?,
+            block: row.get(4)?,
+            open: row.get(5)?,
+            high: row.get(6)?,
+            low: row.get(7)?,
+            close: row.get(8)?,
+            best_bid: row.get(9)?,
+            best_ask: row.get(10)?,
+            bid_depth_1pct: row.get(11)?,
+            ask_depth_1pct: row.get(12)?,
+            bid_depth_5pct: row.get(13)?,
+            ask_depth_5pct: row.get(14)?,
+            range_liquidity: row.get(15)?,
+            samples: row.get(16)?,
+        })
+    }
+
+    /// Combines consecutive snapshots, oldest first, into one at `resolution`.
+    fn aggregate(snapshots: &[PoolSnapshot], resolution: i64) -> PoolSnapshot {
+        let first = &snapshots[0];
+        let last = &snapshots[snapshots.len() - 1];
+        let samples: u32 = snapshots.iter().map(|s| s.samples).sum();
+        let weighted = |f: fn(&PoolSnapshot) -> f64| {
+            snapshots
+                .iter()
+                .map(|s| f(s) * s.samples as f64)
+                .sum::<f64>()
+                / samples as f64
+        };
+        let weighted_opt = |f: fn(&PoolSnapshot) -> Option<f64>| {
+            let (sum, n) = snapshots
+                .iter()
+                .filter_map(|s| f(s).map(|v| (v * s.samples as f64, s.samples)))
+                .fold((0.0, 0), |(sum, n), (v, w)| (sum + v, n + w));
+            (n > 0).then(|| sum / n as f64)
+        };
+        PoolSnapshot {
+            base: first.base.clone(),
+            quote: first.quote.clone(),
+            resolution,
+            timestamp: first.timestamp - first.timestamp.rem_euclid(resolution),
+            block: last.block,
+            open: first.open,
+            high: snapshots.iter().map(|s| s.high).fold(f64::MIN, f64::max),
+            low: snapshots.iter().map(|s| s.low).fold(f64::MAX, f64::min),
+            close: last.close,
+            best_bid: weighted_opt(|s| s.best_bid),
+            best_ask: weighted_opt(|s| s.best_ask),
+            bid_depth_1pct: weighted(|s| s.bid_depth_1pct),
+            ask_depth_1pct: weighted(|s| s.ask_depth_1pct),
+            bid_depth_5pct: weighted(|s| s.bid_depth_5pct),
+            ask_depth_5pct: weighted(|s| s.ask_depth_5pct),
+            range_liquidity: weighted(|s| s.range_liquidity),
+            samples,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AuctionSnapshot {
+    pub block: u32,
+    pub timestamp: i64,
+    pub epoch_started_at: u32,
+    pub blocks_per_epoch: u32,
+    /// In FLIP.
+    pub min_active_bid: f64,
+}
+
+/// The min active bid over a period, from recorded auction snapshots.
+#[derive(Clone, Debug)]
+pub struct AuctionSummary {
+    pub first: f64,
+    pub last: f64,
+    pub low: f64,
+    pub high: f64,
+    /// Distinct epochs seen, so a value above 1 means there were rotations.
+    pub epochs: u32,
+}
+
+const SELECT_SNAPSHOT: &str = "SELECT base, quote, resolution, timestamp, block, open, high, low,
+    close, best_bid, best_ask, bid_depth_1pct, ask_depth_1pct, bid_depth_5pct, ask_depth_5pct,
+    range_liquidity, samples
+    FROM pool_snapshot";
+
+fn insert(conn: &rusqlite::Connection, s: &PoolSnapshot) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO pool_snapshot VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        params![
+            s.base,
+            s.quote,
+            s.resolution,
+            s.timestamp,
+            s.block,
+            s.open,
+            s.high,
+            s.low,
+            s.close,
+            s.best_bid,
+            s.best_ask,
+            s.bid_depth_1pct,
+            s.ask_depth_1pct,
+            s.bid_depth_5pct,
+            s.ask_depth_5pct,
+            s.range_liquidity,
+            s.samples
+        ],
+    )
+}
+
+impl Db {
+    pub fn insert_pool_snapshot(&self, snapshot: &PoolSnapshot) -> Result<(), Error> {
+        self.with(|conn| insert(conn, snapshot))?;
+        Ok(())
+    }
+
+    /// Snapshots of every resolution in `[from, to)`, oldest first.
+    pub fn pool_snapshots(
+        &self,
+        base: &str,
+        quote: &str,
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<PoolSnapshot>, Error> {
+        self.with(|conn| {
+            conn.prepare(&format!(
+                "{SELECT_SNAPSHOT} WHERE base = ?1 AND quote = ?2
+                    AND timestamp >= ?3 AND timestamp < ?4
+                ORDER BY timestamp, resolution"
+            ))?
+            .query_map(params![base, quote, from, to], PoolSnapshot::from_row)?
+            .collect()
+        })
+    }
+
+    /// Number of raw snapshots recorded for a pool since `from`, including downsampled ones.
+    pub fn pool_snapshot_samples_since(
+        &self,
+        base: &str,
+        quote: &str,
+        from: i64,
+    ) -> Result<u32, Error> {
+        self.with(|conn| {
+            conn.query_row(
+                "SELECT COALESCE(SUM(samples), 0) FROM pool_snapshot
+                WHERE base = ?1 AND quote = ?2 AND timestamp >= ?3",
+                params![base, quote, from],
+                |row| row.get(0),
+            )
+        })
+    }
+
+    /// Downsamples snapshots at resolution `from` older than `before` into buckets of `to`
+    /// seconds. `before` should be aligned to `to` so only complete buckets are compacted.
+    pub fn compact_pool_snapshots(&self, from: i64, to: i64, before: i64) -> Result<usize, Error> {
+        self.with(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            let snapshots = tx
+                .prepare(&format!(
+                    "{SELECT_SNAPSHOT} WHERE resolution = ?1 AND timestamp < ?2
+                    ORDER BY base, quote, timestamp"
+                ))?
+                .query_map(params![from, before], PoolSnapshot::from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            let buckets = snapshots.chunk_by(|a, b| {
+                a.base == b.base
+                    && a.quote == b.quote
+                    && a.timestamp.div_euclid(to) == b.timestamp.div_euclid(to)
+            });
+            for bucket in buckets {
+                insert(&tx, &PoolSnapshot::aggregate(bucket, to))?;
+            }
+            tx.execute(
+                "DELETE FROM pool_snapshot WHERE resolution = ?1 AND timestamp < ?2",
+                params![from, before],
+            )?;
+            tx.commit()?;
+            Ok(snapshots.len())
+        })
+    }
+
+    pub fn insert_auction_snapshot(&self, s: &AuctionSnapshot) -> Result<(), Error> {
+        self.with(|conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO auction_snapshot VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    s.block,
+                    s.timestamp,
+                    s.epoch_started_at,
+                    s.blocks_per_epoch,
+                    s.min_active_bid
+                ],
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Time of the oldest snapshot of a pool at or after `from`, i.e. since when it has been
+    /// recorded within the window.
+    pub fn first_pool_snapshot_since(
+        &self,
+        base: &str,
+        quote: &str,
+        from: i64,
+    ) -> Result<Option<i64>, Error> {
+        self.with(|conn| {
+            conn.query_row(
+                "SELECT MIN(timestamp) FROM pool_snapshot
+                WHERE base = ?1 AND quote = ?2 AND timestamp >= ?3",
+                params![base, quote, from],
+                |row| row.get(0),
+            )
+        })
+    }
+
+    /// Summary of the auction snapshots since `from`, if there are any.
+    pub fn auction_summary_since(&self, from: i64) -> Result<Option<AuctionSummary>, Error> {
+        self.with(|conn| {
+            let bid = |order: &str| {
+                conn.query_row(
+                    &format!(
+                        "SELECT min_active_bid FROM auction_snapshot WHERE timestamp >= ?1
+                        ORDER BY timestamp {order} LIMIT 1"
+                    ),
+                    [from],
+                    |row| row.get::<_, f64>(0),
+                )
+                .optional()
+            };
+            let (Some(first), Some(last)) = (bid("ASC")?, bid("DESC")?) else {
+                return Ok(None);
+            };
+            conn.query_row(
+                "SELECT MIN(min_active_bid), MAX(min_active_bid), COUNT(DISTINCT epoch_started_at)
+                FROM auction_snapshot WHERE timestamp >= ?1",
+                [from],
+                |row| {
+                    Ok(Some(AuctionSummary {
+                        first,
+                        last,
+                        low: row.get(0)?,
+                        high: row.get(1)?,
+                        epochs: row.get(2)?,
+                    }))
+                },
+            )
+        })
+    }
+
+    pub fn delete_pool_snapshots(&self, resolution: i64, before: i64) -> Result<usize, Error> {
+        self.with(|conn| {
+            conn.execute(
+                "DELETE FROM pool_snapshot WHERE resolution = ?1 AND timestamp < ?2",
+                params![resolution, before],
+            )
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use super::snapshots::RAW;
+use super::Db;
+use crate::analytics::volume::Fills;
+use crate::Error;
+use rusqlite::params;
+
+impl Db {
+    /// Stores the fills inferred at `timestamp`, replacing any recorded for it before, so
+    /// replaying a block doesn't count it twice. Blocks without fills aren't kept.
+    pub fn insert_pool_fills(
+        &self,
+        base: &str,
+        quote: &str,
+        timestamp: i64,
+        fills: &Fills,
+    ) -> Result<(), Error> {
+        self.with(|conn| match fills.volume == 0.0 && fills.fees() == 0.0 {
+            true => conn.execute(
+                "DELETE FROM pool_fill
+                WHERE base = ?1 AND quote = ?2 AND resolution = ?3 AND timestamp = ?4",
+                params![base, quote, RAW, timestamp],
+            ),
+            false => conn.execute(
+                "INSERT OR REPLACE INTO pool_fill VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    base,
+                    quote,
+                    RAW,
+                    timestamp,
+                    fills.volume,
+                    fills.limit_fees,
+                    fills.range_fees
+                ],
+            ),
+        })?;
+        Ok(())
+    }
+
+    /// Sum of fills in the buckets of any resolution starting at or after `from`.
+    pub fn pool_fills_since(&self, base: &str, quote: &str, from: i64) -> Result<Fills, Error> {
+        self.with(|conn| {
+            conn.query_row(
+                "SELECT TOTAL(volume), TOTAL(limit_fees), TOTAL(range_fees) FROM pool_fill
+                WHERE base = ?1 AND quote = ?2 AND timestamp >= ?3",
+                params![base, quote, from],
+                |row| {
+                    Ok(Fills {
+                        volume: row.get(0)?,
+                        limit_fees: row.get(1)?,
+                        range_fees: row.get(2)?,
+                    })
+                },
+            )
+        })
+    }
+
+    /// Sums fills at resolution `from` older than `before` into buckets of `to` seconds, like
+    /// [`Db::compact_pool_snapshots`].
+    pub fn compact_pool_fills(&self, from: i64, to: i64, before: i64) -> Result<usize, Error> {
+        self.with(|conn| {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "INSERT OR REPLACE INTO pool_fill
+                SELECT base, quote, ?2, bucket, TOTAL(volume), TOTAL(limit_fees), TOTAL(range_fees)
+                FROM (
+                    SELECT *, timestamp - timestamp % ?2 AS bucket FROM pool_fill
+                    WHERE resolution = ?1 AND timestamp < ?3
+                )
+                GROUP BY base, quote, bucket",
+                params![from, to, before],
+            )?;
+            let compacted = tx.execute(
+                "DELETE FROM pool_fill WHERE resolution = ?1 AND timestamp < ?2",
+                params![from, before],
+            )?;
+            tx.commit()?;
+            Ok(compacted)
+        })
+    }
+
+    pub fn delete_pool_fills(&self, resolution: i64, before: i64) -> Result<usize, Error> {
+        self.with(|conn| {
+            conn.execute(
+                "DELETE FROM pool_fill WHERE resolution = ?1 AND timestamp < ?2",
+                params![resolution, before],
+            )
+        })
+    }
+}
@@ -0,0 +1,98 @@
+use super::Db;
+use crate::Error;
+use rusqlite::{params, Row};
+
+#[derive(Clone, Debug)]
+pub struct ValidatorWatch {
+    pub id: i64,
+    pub user_id: u64,
+    pub channel_id: u64,
+    pub account: String,
+    pub network: String,
+    pub min_reputation: Option<i32>,
+    pub max_heartbeat_lag: Option<u32>,
+}
+
+impl ValidatorWatch {
+    fn from_row(row: &Row) -> rusqlite::Result<ValidatorWatch> {
+        Ok(ValidatorWatch {
+            id: row.get(0)?,
+            user_id: row.get::<_, i64>(1)? as u64,
+            channel_id: row.get::<_, i64>(2)? as u64,
+            account: row.get(3)?,
+            network: row.get(4)?,
+            min_reputation: row.get(5)?,
+            max_heartbeat_lag: row.get(6)?,
+        })
+    }
+}
+
+const SELECT_WATCH: &str =
+    "SELECT id, user_id, channel_id, account, network, min_reputation, max_heartbeat_lag
+    FROM validator_watch";
+
+impl Db {
+    /// Adds a watch, or updates the channel and thresholds of an existing one.
+    pub fn upsert_validator_watch(
+        &self,
+        user_id: u64,
+        channel_id: u64,
+        account: &str,
+        network: &str,
+        min_reputation: Option<i32>,
+        max_heartbeat_lag: Option<u32>,
+    ) -> Result<(), Error> {
+        self.with(|conn| {
+            conn.execute(
+                "INSERT INTO validator_watch
+                    (user_id, channel_id, account, network, min_reputation, max_heartbeat_lag)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                ON CONFLICT (user_id, account, network) DO UPDATE SET
+                    channel_id = excluded.channel_id,
+                    min_reputation = excluded.min_reputation,
+                    max_heartbeat_lag = excluded.max_heartbeat_lag",
+                params![
+                    user_id as i64,
+                    channel_id as i64,
+                    account,
+                    network,
+                    min_reputation,
+                    max_heartbeat_lag
+                ],
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Returns whether a watch was removed.
+    pub fn remove_validator_watch(
+        &self,
+        user_id: u64,
+        account: &str,
+        network: &str,
+    ) -> Result<bool, Error> {
+        let removed = self.with(|conn| {
+            conn.execute(
+                "DELETE FROM validator_watch WHERE user_id = ?1 AND account = ?2 AND network = ?3",
+                params![user_id as i64, account, network],
+            )
+        })?;
+        Ok(removed > 0)
+    }
+
+    pub fn validator_watches(&self, network: &str) -> Result<Vec<ValidatorWatch>, Error> {
+        self.with(|conn| {
+            conn.prepare(&format!("{SELECT_WATCH} WHERE network = ?1"))?
+                .query_map([network], ValidatorWatch::from_row)?
+                .collect()
+        })
+    }
+
+    pub fn validator_watches_for_user(&self, user_id: u64) -> Result<Vec<ValidatorWatch>, Error> {
+        self.with(|conn| {
+            conn.prepare(&format!("{SELECT_WATCH} WHERE user_id = ?1"))?
+                .query_map([user_id as i64], ValidatorWatch::from_row)?
+                .collect()
+        })
+    }
+}
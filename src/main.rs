@@ -1,43 +1,244 @@
+mod analytics;
+mod backfill;
 mod commands;
+mod config;
+mod db;
+mod network;
+mod prices;
+mod rpc;
+mod schedule;
+mod settings;
+mod tasks;
 mod util;
 
-use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use backfill::BackfillArgs;
+use config::{Colours, Config, ConfigErrors, Mode, RegistrationMode, RegistrationSettings};
+use db::Db;
+use network::Networks;
 use poise::serenity_prelude::{self as serenity};
+use prices::PriceFeed;
+use rpc::heads;
+use rpc::pool::RpcPool;
+use settings::SettingsService;
+use std::sync::Arc;
+use tasks::{arb_alerts, dashboards, digests, presence, recorder, swap_feed, validator_alerts};
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
 type Context<'a> = poise::Context<'a, Data, Error>;
 
 #[derive(Debug)]
 pub struct Data {
-    http_client: HttpClient,
+    networks: Networks,
+    db: Db,
+    settings: SettingsService,
+    price_feed: Option<Arc<dyn PriceFeed>>,
+    colours: Colours,
+    rpc_limit: commands::RpcLimit,
+    default_prefix: String,
+    registration: RegistrationSettings,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let token =
-        std::env::var("JITCORD_DISCORD_TOKEN").expect("missing JITCORD_DISCORD_TOKEN env var!");
-    let target = std::env::var("JITCORD_TARGET").expect("missing JITCORD_TARGET env var!");
-    let intents = serenity::GatewayIntents::non_privileged();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("backfill") {
+        return run_backfill(BackfillArgs::parse(&args[1..])?).await;
+    }
+    let config = load_config(Mode::Bot);
+    let price_feed = config
+        .price_feed
+        .as_ref()
+        .map(prices::from_config)
+        .transpose()?;
+    let Config {
+        discord_token,
+        networks: network_configs,
+        default_network,
+        health_interval,
+        colours,
+        db_path,
+        command_groups,
+        alerts: alert_settings,
+        dashboards: dashboard_settings,
+        presence: presence_settings,
+        swap_feed: swap_feed_settings,
+        recorder: recorder_settings,
+        arb_alerts: arb_alert_settings,
+        prefix,
+        registration,
+        owners,
+        cooldowns,
+        rpc_limit,
+        ..
+    } = config;
+    let mut intents = serenity::GatewayIntents::non_privileged();
+    // Without it only messages mentioning the bot, and DMs, have content to parse commands from.
+    if prefix.message_content {
+        intents |= serenity::GatewayIntents::MESSAGE_CONTENT;
+    }
+
+    let mut commands: Vec<_> = vec![
+        commands::cf::cf(),
+        commands::lp::lp(),
+        commands::alerts::alerts(),
+        commands::config::config(),
+        commands::dashboard::dashboard(),
+        commands::digest::digest(),
+        commands::help::help(),
+        commands::admin::admin(),
+    ]
+    .into_iter()
+    .filter(|command| command_groups.contains(&command.name))
+    .collect();
+    // Poise ignores edited messages unless the command opts in.
+    if prefix.edit_tracking.is_some() {
+        commands::track_edits(&mut commands);
+    }
+    let unknown = commands::apply_cooldowns(&commands, &cooldowns);
+    if !unknown.is_empty() {
+        let errors = unknown
+            .iter()
+            .map(|name| format!("cooldowns.commands: unknown command `{name}`"))
+            .collect();
+        eprint!("{}", ConfigErrors(errors));
+        std::process::exit(1);
+    }
 
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![commands::cf::cf(), commands::lp::lp()],
+            commands,
+            command_check: Some(|ctx| Box::pin(commands::command_check(ctx))),
+            owners: owners.into_iter().map(serenity::UserId::new).collect(),
+            on_error: |error| Box::pin(commands::on_error(error)),
+            prefix_options: poise::PrefixFrameworkOptions {
+                dynamic_prefix: Some(|ctx| Box::pin(commands::prefix(ctx))),
+                mention_as_prefix: true,
+                edit_tracker: prefix
+                    .edit_tracking
+                    .map(|timespan| Arc::new(poise::EditTracker::for_timespan(timespan))),
+                ..Default::default()
+            },
             ..Default::default()
         })
-        .setup(|ctx, _ready, framework| {
+        .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                let client = HttpClientBuilder::default().build(target).unwrap();
+                if registration.mode != RegistrationMode::Off {
+                    let commands = &framework.options().commands;
+                    // `/admin register` can retry once Discord accepts the commands again.
+                    if let Err(err) =
+                        commands::admin::sync(&ctx.http, commands, &registration).await
+                    {
+                        eprintln!("registration: {err}");
+                    }
+                }
+                let networks = Networks::new(&network_configs, &default_network)?;
+                let db = Db::open(&db_path)?;
+                let settings = SettingsService::new(db.clone());
+                let mut default_blocks = None;
+                for (network, config) in networks.iter().zip(&network_configs) {
+                    network.rpc.spawn_probes(health_interval);
+                    let blocks = heads::spawn(config.ws_endpoints.clone(), network.rpc.clone());
+                    network.rpc.spawn_cache_invalidation(blocks.subscribe());
+                    validator_alerts::spawn(
+                        ctx.http.clone(),
+                        network.clone(),
+                        db.clone(),
+                        alert_settings.clone(),
+                        blocks.subscribe(),
+                    );
+                    dashboards::spawn(
+                        ctx.http.clone(),
+                        network.clone(),
+                        db.clone(),
+                        settings.clone(),
+                        dashboard_settings,
+                        blocks.subscribe(),
+                    );
+                    if network.recorded {
+                        default_blocks = Some(blocks);
+                    }
+                }
+                digests::spawn(
+                    ctx.http.clone(),
+                    networks.iter().cloned().collect(),
+                    db.clone(),
+                    settings.clone(),
+                );
+                // History, the presence, the swap feed and arbitrage alerts only follow the default
+                // network.
+                let network = networks.default().clone();
+                let blocks = default_blocks.ok_or("default network not started")?;
+                recorder::spawn(
+                    network.clone(),
+                    db.clone(),
+                    recorder_settings,
+                    blocks.subscribe(),
+                );
+                if let Some(settings) = presence_settings {
+                    presence::spawn(ctx.clone(), network.clone(), settings, blocks.subscribe());
+                }
+                if let Some(settings) = swap_feed_settings {
+                    swap_feed::spawn(
+                        ctx.http.clone(),
+                        network.clone(),
+                        settings,
+                        blocks.subscribe(),
+                    );
+                }
+                if let (Some(feed), Some(settings)) = (&price_feed, arb_alert_settings) {
+                    arb_alerts::spawn(
+                        ctx.http.clone(),
+                        network,
+                        feed.clone(),
+                        settings,
+                        blocks.subscribe(),
+                    );
+                }
                 Ok(Data {
-                    http_client: client,
+                    networks,
+                    settings,
+                    db,
+                    price_feed,
+                    colours,
+                    rpc_limit: commands::RpcLimit::new(rpc_limit),
+                    default_prefix: prefix.default,
+                    registration,
                 })
             })
         })
         .build();
 
-    let client = serenity::ClientBuilder::new(token, intents)
+    serenity::ClientBuilder::new(discord_token, intents)
         .framework(framework)
-        .await;
-    client.unwrap().start().await.unwrap();
+        .await?
+        .start()
+        .await?;
     Ok(())
 }
+
+async fn run_backfill(args: BackfillArgs) -> Result<(), Error> {
+    let config = load_config(Mode::Backfill);
+    let network = config
+        .networks
+        .into_iter()
+        .find(|n| n.name == config.default_network)
+        .ok_or("default network not configured")?;
+    let archive = match (&args.archive, network.archive) {
+        (Some(archive), _) => archive.clone(),
+        (None, Some(archive)) => archive,
+        (None, None) => {
+            return Err("missing --archive or an archive for the default network".into())
+        }
+    };
+    let rpc = RpcPool::new(&[archive])?;
+    let db = Db::open(&config.db_path)?;
+    backfill::run(args, rpc, network.assets, db).await
+}
+
+/// Loads the config or exits after listing every problem with it.
+fn load_config(mode: Mode) -> Config {
+    Config::load(mode).unwrap_or_else(|errors| {
+        eprint!("{errors}");
+        std::process::exit(1);
+    })
+}
@@ -0,0 +1,74 @@
+use crate::config::NetworkConfig;
+use crate::rpc::pool::RpcPool;
+use crate::Error;
+use poise::serenity_prelude::{Colour, CreateEmbed, CreateEmbedAuthor};
+
+/// A chain the bot talks to, e.g. mainnet or the perseverance testnet, with its own
+/// endpoints, cache and pools.
+#[derive(Clone, Debug)]
+pub struct Network {
+    pub name: String,
+    pub rpc: RpcPool,
+    /// Assets with pools on this network.
+    pub assets: Vec<String>,
+    pub colour: Colour,
+    /// Whether the recorder keeps history for this network, which is only the default one.
+    pub recorded: bool,
+}
+
+impl Network {
+    pub fn new(config: &NetworkConfig, recorded: bool) -> Result<Network, Error> {
+        Ok(Network {
+            name: config.name.clone(),
+            rpc: RpcPool::new(&config.endpoints)?,
+            assets: config.assets.clone(),
+            colour: config.colour,
+            recorded,
+        })
+    }
+
+    pub fn supports(&self, asset: &str) -> bool {
+        self.assets.iter().any(|a| a == asset)
+    }
+
+    /// Marks an embed with the network it was built from.
+    pub fn tag(&self, embed: CreateEmbed) -> CreateEmbed {
+        embed.author(CreateEmbedAuthor::new(&self.name))
+    }
+}
+
+#[derive(Debug)]
+pub struct Networks {
+    networks: Vec<Network>,
+    default: usize,
+}
+
+impl Networks {
+    pub fn new(configs: &[NetworkConfig], default: &str) -> Result<Networks, Error> {
+        let networks = configs
+            .iter()
+            .map(|config| Network::new(config, config.name == default))
+            .collect::<Result<Vec<_>, _>>()?;
+        let default = networks
+            .iter()
+            .position(|n| n.name == default)
+            .ok_or(format!("unknown default network: {default}"))?;
+        Ok(Networks { networks, default })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Network> {
+        self.networks.iter().find(|n| n.name == name)
+    }
+
+    pub fn default(&self) -> &Network {
+        &self.networks[self.default]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Network> {
+        self.networks.iter()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.networks.iter().map(|n| n.name.as_str()).collect()
+    }
+}
@@ -0,0 +1,67 @@
+use super::{json_price, PriceFeed};
+use crate::Error;
+use async_trait::async_trait;
+
+/// Reads prices from a JSON file such as `{"BTC/USDC": 64000.5}`. The file is read on every
+/// lookup, so it can be edited while the bot runs.
+#[derive(Debug)]
+pub struct StaticFileFeed {
+    path: String,
+}
+
+impl StaticFileFeed {
+    pub fn new(path: String) -> StaticFileFeed {
+        StaticFileFeed { path }
+    }
+}
+
+#[async_trait]
+impl PriceFeed for StaticFileFeed {
+    async fn price(&self, base: &str, quote: &str) -> Result<f64, Error> {
+        let contents = tokio::fs::read_to_string(&self.path).await?;
+        let prices: serde_json::Value = serde_json::from_str(&contents)?;
+        prices
+            .get(format!("{}/{}", base, quote))
+            .and_then(json_price)
+            .ok_or(format!("no price for {}/{} in {}", base, quote, self.path).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn write_prices(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn reads_numbers_and_numeric_strings() {
+        let path = write_prices(
+            "prices-read",
+            r#"{"BTC/USDC": 64000.5, "ETH/USDC": "3100.25"}"#,
+        );
+        let feed = StaticFileFeed::new(path.to_string_lossy().into_owned());
+        assert_eq!(feed.price("BTC", "USDC").await.unwrap(), 64000.5);
+        assert_eq!(feed.price("ETH", "USDC").await.unwrap(), 3100.25);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_pair_is_an_error() {
+        let path = write_prices("prices-missing", r#"{"BTC/USDC": 64000.5}"#);
+        let feed = StaticFileFeed::new(path.to_string_lossy().into_owned());
+        let err = feed.price("USDC", "BTC").await.unwrap_err();
+        assert!(err.to_string().starts_with("no price for USDC/BTC in "));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_file_is_an_error() {
+        let feed = StaticFileFeed::new("/nonexistent/prices.json".to_string());
+        assert!(feed.price("BTC", "USDC").await.is_err());
+    }
+}
@@ -0,0 +1,44 @@
+use super::{json_price, PriceFeed};
+use crate::Error;
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// A feed that doesn't answer within this is treated as failed, so it can't stall the caller.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Fetches prices from a JSON HTTP endpoint. `{base}` and `{quote}` in the URL are replaced
+/// with the assets, and the price is read at a JSON pointer such as `/data/price`.
+#[derive(Debug)]
+pub struct HttpJsonFeed {
+    client: reqwest::Client,
+    url: String,
+    pointer: String,
+}
+
+impl HttpJsonFeed {
+    pub fn new(url: String, pointer: String) -> Result<HttpJsonFeed, Error> {
+        Ok(HttpJsonFeed {
+            client: reqwest::Client::builder().timeout(TIMEOUT).build()?,
+            url,
+            pointer,
+        })
+    }
+}
+
+#[async_trait]
+impl PriceFeed for HttpJsonFeed {
+    async fn price(&self, base: &str, quote: &str) -> Result<f64, Error> {
+        let url = self.url.replace("{base}", base).replace("{quote}", quote);
+        let body: serde_json::Value = self
+            .client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        body.pointer(&self.pointer)
+            .and_then(json_price)
+            .ok_or(format!("no price at `{}` in response from {}", self.pointer, url).into())
+    }
+}
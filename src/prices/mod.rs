@@ -0,0 +1,34 @@
+pub mod file;
+pub mod http;
+
+use crate::config::PriceFeedConfig;
+use crate::Error;
+use async_trait::async_trait;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// A source of reference prices to compare pool prices against.
+#[async_trait]
+pub trait PriceFeed: Debug + Send + Sync {
+    /// Price of one `base` in `quote`.
+    async fn price(&self, base: &str, quote: &str) -> Result<f64, Error>;
+}
+
+/// Builds the configured feed: an HTTP endpoint or a static file.
+pub fn from_config(config: &PriceFeedConfig) -> Result<Arc<dyn PriceFeed>, Error> {
+    Ok(match config {
+        PriceFeedConfig::Http { url, pointer } => {
+            Arc::new(http::HttpJsonFeed::new(url.clone(), pointer.clone())?)
+        }
+        PriceFeedConfig::File { path } => Arc::new(file::StaticFileFeed::new(path.clone())),
+    })
+}
+
+/// Reads a price that may be encoded as a JSON number or a numeric string.
+fn json_price(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,73 @@
+use super::pool::RpcPool;
+use crate::Error;
+use jsonrpsee::rpc_params;
+use web3::types::H256;
+
+/// The block a query is made at: the latest one, or a historical block resolved to its hash.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockAt {
+    pub number: Option<u32>,
+    /// Passed as the trailing `at` param of state queries; `None` means latest.
+    pub hash: Option<H256>,
+}
+
+impl BlockAt {
+    pub async fn resolve(rpc: &RpcPool, number: Option<u32>) -> Result<BlockAt, Error> {
+        let Some(number) = number else {
+            return Ok(BlockAt::default());
+        };
+        let hash: Option<H256> = rpc
+            .request("chain_getBlockHash", rpc_params![number])
+            .await
+            .map_err(|err| format!("Request failed: {err}"))?;
+        match hash {
+            Some(hash) => Ok(BlockAt {
+                number: Some(number),
+                hash: Some(hash),
+            }),
+            None => Err(format!("Block {} not found", number).into()),
+        }
+    }
+
+    pub fn is_latest(&self) -> bool {
+        self.hash.is_none()
+    }
+
+    /// Turns a failed state query into a readable error, calling out pruned state.
+    pub fn request_error(&self, err: Error) -> Error {
+        let message = err.to_string();
+        let lowercase = message.to_lowercase();
+        match self.number {
+            Some(number)
+                if lowercase.contains("discarded")
+                    || lowercase.contains("pruned")
+                    || lowercase.contains("unknown block") =>
+            {
+                format!(
+                    "State at block {} has been pruned by the node, an archive node is needed for this query",
+                    number
+                )
+                .into()
+            }
+            _ => format!("Request failed: {message}").into(),
+        }
+    }
+}
+
+// twox128("Timestamp") ++ twox128("Now")
+const TIMESTAMP_NOW_KEY: &str =
+    "0xf0c365c3cf59d671eb72da0e7a4113c49f1f0515f462cdcf84e0f1d6045dfcbb";
+
+/// Unix timestamp (in seconds) of a block, read from the timestamp pallet.
+pub async fn block_timestamp(rpc: &RpcPool, hash: H256) -> Result<i64, Error> {
+    let value: Option<String> = rpc
+        .request("state_getStorage", rpc_params![TIMESTAMP_NOW_KEY, hash])
+        .await?;
+    let value = value.ok_or("block has no timestamp")?;
+    let bytes = (0..8)
+        .map(|i| u8::from_str_radix(value.get(2 + i * 2..4 + i * 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(format!("invalid timestamp: {value}"))?;
+    let millis = u64::from_le_bytes(bytes.try_into().unwrap());
+    Ok((millis / 1000) as i64)
+}
@@ -0,0 +1,145 @@
+use super::heads::{self, BlockEvent};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, OnceCell};
+
+/// Block scoped responses are dropped after this long even without a new head, so they
+/// can't go stale while the head feed is down. About five block times.
+const MAX_BLOCK_AGE: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Ttl {
+    Fixed(Duration),
+    /// Valid until the next new head, or [`MAX_BLOCK_AGE`].
+    Block,
+}
+
+fn ttl(method: &str) -> Option<Ttl> {
+    match method {
+        "cf_accounts" => Some(Ttl::Fixed(Duration::from_secs(600))),
+        "cf_environment" => Some(Ttl::Fixed(Duration::from_secs(3600))),
+        "chain_getHeader"
+        | "cf_account_info"
+        | "cf_auction_state"
+        | "cf_current_epoch"
+        | "cf_current_epoch_started_at"
+        | "cf_pool_orders"
+        | "cf_pool_price"
+        | "cf_scheduled_swaps" => Some(Ttl::Block),
+        _ => None,
+    }
+}
+
+type Key = (String, Option<String>);
+
+#[derive(Debug)]
+struct Entry {
+    value: Arc<OnceCell<Arc<Value>>>,
+    created: Instant,
+    ttl: Ttl,
+}
+
+impl Entry {
+    fn is_fresh(&self) -> bool {
+        match self.ttl {
+            Ttl::Fixed(ttl) => self.created.elapsed() < ttl,
+            Ttl::Block => self.created.elapsed() < MAX_BLOCK_AGE,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Caches responses per method and params. Identical requests made while one is in flight
+/// wait for it instead of hitting the node again.
+#[derive(Debug, Default)]
+pub struct Cache {
+    entries: Mutex<HashMap<Key, Entry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Cache {
+    pub async fn get_or_fetch<F, Fut, E>(
+        &self,
+        method: &str,
+        params: Option<String>,
+        fetch: F,
+    ) -> Result<Arc<Value>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, E>>,
+    {
+        let Some(ttl) = ttl(method) else {
+            return fetch().await.map(Arc::new);
+        };
+        let key = (method.to_string(), params);
+        let cell = {
+            let mut entries = self.entries.lock().unwrap();
+            match entries.get(&key) {
+                Some(entry) if entry.is_fresh() => {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    entry.value.clone()
+                }
+                _ => {
+                    self.misses.fetch_add(1, Ordering::Relaxed);
+                    let entry = Entry {
+                        value: Arc::new(OnceCell::new()),
+                        created: Instant::now(),
+                        ttl,
+                    };
+                    let cell = entry.value.clone();
+                    entries.insert(key.clone(), entry);
+                    cell
+                }
+            }
+        };
+        let result = cell
+            .get_or_try_init(|| async { fetch().await.map(Arc::new) })
+            .await
+            .cloned();
+        if result.is_err() {
+            // Drop the failed entry so the next caller retries, unless it was already replaced.
+            let mut entries = self.entries.lock().unwrap();
+            if entries
+                .get(&key)
+                .is_some_and(|entry| Arc::ptr_eq(&entry.value, &cell))
+            {
+                entries.remove(&key);
+            }
+        }
+        result
+    }
+
+    /// Drops everything that is only valid for the current block, and anything expired.
+    pub fn invalidate_block(&self) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|_, entry| entry.ttl != Ttl::Block && entry.is_fresh());
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn spawn_invalidation(self: &Arc<Self>, mut blocks: broadcast::Receiver<BlockEvent>) {
+        let cache = self.clone();
+        tokio::spawn(async move {
+            while heads::next_head(&mut blocks).await.is_some() {
+                cache.invalidate_block();
+            }
+        });
+    }
+}
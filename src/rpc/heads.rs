@@ -0,0 +1,164 @@
+use super::pool::RpcPool;
+use crate::commands::cf::BlockHeader;
+use crate::Error;
+use jsonrpsee::core::client::{Subscription, SubscriptionClientT};
+use jsonrpsee::rpc_params;
+use jsonrpsee::ws_client::WsClientBuilder;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use web3::types::H256;
+
+const CHANNEL_CAPACITY: usize = 64;
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// How often heads are polled over HTTP while no WebSocket is connected, about a block time.
+const POLL_INTERVAL: Duration = Duration::from_secs(6);
+
+#[derive(Clone, Debug)]
+pub enum BlockEvent {
+    New(BlockHeader),
+    #[allow(dead_code)]
+    Finalized(BlockHeader),
+}
+
+/// Derives the WebSocket endpoint from the HTTP one, as nodes serve both on the same port.
+pub fn ws_url(http_url: &str) -> String {
+    match http_url.split_once("://") {
+        Some(("https", rest)) => format!("wss://{rest}"),
+        Some(("http", rest)) => format!("ws://{rest}"),
+        _ => http_url.to_string(),
+    }
+}
+
+/// Headers don't carry their own hash, these are the fields it is derived from.
+type HeaderKey = (u64, H256, H256, H256);
+
+fn key(header: &BlockHeader) -> HeaderKey {
+    (
+        header.number.as_u64(),
+        header.parentHash,
+        header.stateRoot,
+        header.extrinsicsRoot,
+    )
+}
+
+/// Publishes each head once, whichever source sees it first.
+struct Feed {
+    sender: broadcast::Sender<BlockEvent>,
+    /// Whether a WebSocket subscription is live, polling only runs while none is.
+    subscribed: AtomicBool,
+    latest_new: Mutex<Option<HeaderKey>>,
+    latest_finalized: Mutex<Option<HeaderKey>>,
+}
+
+impl Feed {
+    /// Publishes `event` unless it repeats the last head of its kind. A different head at the
+    /// same or a lower height is a reorg and is published too.
+    fn publish(&self, event: BlockEvent) {
+        let (latest, header) = match &event {
+            BlockEvent::New(header) => (&self.latest_new, header),
+            BlockEvent::Finalized(header) => (&self.latest_finalized, header),
+        };
+        let key = key(header);
+        if latest.lock().unwrap().replace(key) != Some(key) {
+            // Sending only fails while nobody is subscribed, which is fine to ignore.
+            let _ = self.sender.send(event);
+        }
+    }
+}
+
+/// Subscribes to new and finalized heads, reconnecting with backoff whenever the socket drops.
+/// Each reconnect moves on to the next of `urls`. While no subscription is live, heads are
+/// polled from `rpc` instead.
+pub fn spawn(urls: Vec<String>, rpc: RpcPool) -> broadcast::Sender<BlockEvent> {
+    let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+    let feed = Arc::new(Feed {
+        sender: tx.clone(),
+        subscribed: AtomicBool::new(false),
+        latest_new: Mutex::new(None),
+        latest_finalized: Mutex::new(None),
+    });
+    let subscription_feed = feed.clone();
+    tokio::spawn(async move {
+        let mut backoff = MIN_BACKOFF;
+        for url in urls.iter().cycle() {
+            let result = follow(url, &subscription_feed, &mut backoff).await;
+            subscription_feed.subscribed.store(false, Ordering::Relaxed);
+            match result {
+                Ok(()) => eprintln!("block subscription: connection to {url} closed"),
+                Err(err) => eprintln!("block subscription: {err}"),
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if feed.subscribed.load(Ordering::Relaxed) {
+                continue;
+            }
+            if let Err(err) = poll(&rpc, &feed).await {
+                eprintln!("block polling: {err}");
+            }
+        }
+    });
+    tx
+}
+
+async fn poll(rpc: &RpcPool, feed: &Feed) -> Result<(), Error> {
+    let header: BlockHeader = rpc.request_fresh("chain_getHeader", rpc_params![]).await?;
+    feed.publish(BlockEvent::New(header));
+    let hash: H256 = rpc.request("chain_getFinalizedHead", rpc_params![]).await?;
+    let header: BlockHeader = rpc.request("chain_getHeader", rpc_params![hash]).await?;
+    feed.publish(BlockEvent::Finalized(header));
+    Ok(())
+}
+
+async fn follow(url: &str, feed: &Feed, backoff: &mut Duration) -> Result<(), Error> {
+    let client = WsClientBuilder::default().build(url).await?;
+    let mut new_heads: Subscription<BlockHeader> = client
+        .subscribe(
+            "chain_subscribeNewHeads",
+            rpc_params![],
+            "chain_unsubscribeNewHeads",
+        )
+        .await?;
+    let mut finalized_heads: Subscription<BlockHeader> = client
+        .subscribe(
+            "chain_subscribeFinalizedHeads",
+            rpc_params![],
+            "chain_unsubscribeFinalizedHeads",
+        )
+        .await?;
+    feed.subscribed.store(true, Ordering::Relaxed);
+    *backoff = MIN_BACKOFF;
+    loop {
+        let event = tokio::select! {
+            header = new_heads.next() => match header {
+                Some(header) => BlockEvent::New(header?),
+                None => return Ok(()),
+            },
+            header = finalized_heads.next() => match header {
+                Some(header) => BlockEvent::Finalized(header?),
+                None => return Ok(()),
+            },
+        };
+        feed.publish(event);
+    }
+}
+
+/// Waits for the next new (not finalized) head, skipping any that were missed while lagging.
+/// Returns `None` once the sender is gone.
+pub async fn next_head(rx: &mut broadcast::Receiver<BlockEvent>) -> Option<BlockHeader> {
+    loop {
+        match rx.recv().await {
+            Ok(BlockEvent::New(header)) => return Some(header),
+            Ok(BlockEvent::Finalized(_)) | Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod block_at;
+pub mod cache;
+pub mod heads;
+pub mod pool;
@@ -0,0 +1,201 @@
+use super::cache::{Cache, CacheStats};
+use super::heads::BlockEvent;
+use crate::commands::cf::SystemHealth;
+use crate::Error;
+use jsonrpsee::core::client::{ClientT, Error as ClientError};
+use jsonrpsee::core::params::ArrayParams;
+use jsonrpsee::core::traits::ToRpcParams;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::rpc_params;
+use serde::de::DeserializeOwned;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+#[derive(Clone, Debug, Default)]
+pub struct EndpointHealth {
+    pub version: Option<String>,
+    pub peers: Option<u32>,
+    pub is_syncing: bool,
+    pub latency: Option<Duration>,
+    /// Set when the last probe or request failed at the transport level.
+    pub error: Option<String>,
+}
+
+impl EndpointHealth {
+    // Lower is better: reachable and synced, then reachable but syncing, then unreachable.
+    fn rank(&self) -> (u8, Duration) {
+        let state = match (&self.error, self.is_syncing) {
+            (None, false) => 0,
+            (None, true) => 1,
+            (Some(_), _) => 2,
+        };
+        (state, self.latency.unwrap_or(Duration::MAX))
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    client: HttpClient,
+    health: RwLock<EndpointHealth>,
+}
+
+impl Endpoint {
+    fn health(&self) -> EndpointHealth {
+        self.health.read().unwrap().clone()
+    }
+
+    fn mark_failed(&self, err: &ClientError) {
+        self.health.write().unwrap().error = Some(err.to_string());
+    }
+
+    async fn probe(&self) {
+        let started = Instant::now();
+        let health = self
+            .client
+            .request::<SystemHealth, _>("system_health", rpc_params![])
+            .await;
+        let latency = started.elapsed();
+        let version = self
+            .client
+            .request::<String, _>("system_version", rpc_params![])
+            .await;
+        let mut state = self.health.write().unwrap();
+        match health {
+            Ok(health) => {
+                state.peers = Some(health.peers);
+                state.is_syncing = health.isSyncing;
+                state.latency = Some(latency);
+                state.error = None;
+            }
+            Err(err) => state.error = Some(err.to_string()),
+        }
+        if let Ok(version) = version {
+            state.version = Some(version);
+        }
+    }
+}
+
+/// Routes requests to the healthiest synced endpoint, failing over to the next one on
+/// transport errors. Responses are cached per method, see [`Cache`].
+#[derive(Clone, Debug)]
+pub struct RpcPool {
+    endpoints: Arc<Vec<Endpoint>>,
+    cache: Arc<Cache>,
+}
+
+impl RpcPool {
+    pub fn new(urls: &[String]) -> Result<RpcPool, Error> {
+        if urls.is_empty() {
+            return Err("no RPC endpoints configured".into());
+        }
+        let endpoints = urls
+            .iter()
+            .map(|url| {
+                Ok(Endpoint {
+                    url: url.clone(),
+                    client: HttpClientBuilder::default().build(url)?,
+                    health: RwLock::new(EndpointHealth::default()),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(RpcPool {
+            endpoints: Arc::new(endpoints),
+            cache: Arc::new(Cache::default()),
+        })
+    }
+
+    pub fn spawn_cache_invalidation(&self, blocks: broadcast::Receiver<BlockEvent>) {
+        self.cache.spawn_invalidation(blocks);
+    }
+
+    /// Drops cached responses that are only valid for the current block.
+    pub fn invalidate_block_cache(&self) {
+        self.cache.invalidate_block();
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    pub fn spawn_probes(&self, interval: Duration) {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                pool.probe_all().await;
+            }
+        });
+    }
+
+    pub async fn probe_all(&self) {
+        futures::future::join_all(self.endpoints.iter().map(Endpoint::probe)).await;
+    }
+
+    /// Health of every endpoint, best first.
+    pub fn health(&self) -> Vec<(String, EndpointHealth)> {
+        self.ordered()
+            .into_iter()
+            .map(|endpoint| (endpoint.url.clone(), endpoint.health()))
+            .collect()
+    }
+
+    fn ordered(&self) -> Vec<&Endpoint> {
+        let mut endpoints: Vec<_> = self.endpoints.iter().collect();
+        endpoints.sort_by_key(|endpoint| endpoint.health().rank());
+        endpoints
+    }
+
+    pub async fn request<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: ArrayParams,
+    ) -> Result<R, Error> {
+        let key = params
+            .clone()
+            .to_rpc_params()?
+            .map(|raw| raw.get().to_string());
+        let value = self
+            .cache
+            .get_or_fetch(method, key, || self.request_uncached(method, params))
+            .await?;
+        Ok(R::deserialize(&*value)?)
+    }
+
+    /// Like [`RpcPool::request`] but always asks a node, for polling what the cache would keep
+    /// until the next block.
+    pub async fn request_fresh<R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: ArrayParams,
+    ) -> Result<R, Error> {
+        Ok(R::deserialize(
+            self.request_uncached(method, params).await?,
+        )?)
+    }
+
+    async fn request_uncached(
+        &self,
+        method: &str,
+        params: ArrayParams,
+    ) -> Result<serde_json::Value, Error> {
+        let mut last_error = None;
+        for endpoint in self.ordered() {
+            match endpoint.client.request(method, params.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(
+                    err @ (ClientError::Transport(_)
+                    | ClientError::RestartNeeded(_)
+                    | ClientError::RequestTimeout),
+                ) => {
+                    endpoint.mark_failed(&err);
+                    last_error = Some(err);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Err(last_error.map_or("no RPC endpoints available".into(), Into::into))
+    }
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+use std::str::FromStr;
+use time::OffsetDateTime as DateTime;
+
+/// A cron expression of minute, hour, day of month, month and day of week, e.g. `0 9 * * 1`
+/// for Mondays at 9:00. Fields take `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps
+/// (`*/15`, `0-30/10`). Days of the week count from Sunday as 0 or 7.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    source: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Like cron, a restricted day of month or day of week matches on either when both are.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    /// Whether the schedule fires in the minute of `at`, which should be in the local time the
+    /// schedule is meant for.
+    pub fn matches(&self, at: DateTime) -> bool {
+        let bit = |set: u64, value: u8| set & (1 << value) != 0;
+        let day = bit(self.days, at.day());
+        let weekday = bit(self.weekdays, at.weekday().number_days_from_sunday());
+        let day = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        };
+        bit(self.minutes, at.minute())
+            && bit(self.hours, at.hour())
+            && bit(self.months, at.month() as u8)
+            && day
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Schedule, String> {
+        let fields = source.split_whitespace().collect::<Vec<_>>();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+        let mut weekdays_set = field(weekdays, 0, 7, "weekday")?;
+        // Sunday is both 0 and 7.
+        if weekdays_set & (1 << 7) != 0 {
+            weekdays_set |= 1;
+        }
+        Ok(Schedule {
+            source: fields.join(" "),
+            minutes: field(minutes, 0, 59, "minute")?,
+            hours: field(hours, 0, 23, "hour")?,
+            days: field(days, 1, 31, "day")?,
+            months: field(months, 1, 12, "month")?,
+            weekdays: weekdays_set,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Parses a cron field into a bit set of the values it matches.
+fn field(field: &str, min: u8, max: u8, name: &str) -> Result<u64, String> {
+    let number = |value: &str| {
+        value
+            .parse::<u8>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or(format!("{name}: expected {min} to {max}, got {value:?}"))
+    };
+    let mut set = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u8>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("{name}: invalid step {step:?}")),
+            },
+            None => (part, 1),
+        };
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (number(from)?, number(to)?),
+            // `5/10` runs from 5 to the end, like cron.
+            None if step > 1 => (number(range)?, max),
+            None => (number(range)?, number(range)?),
+        };
+        if from > to {
+            return Err(format!("{name}: {from}-{to} is an empty range"));
+        }
+        for value in (from..=to).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month, Time};
+    use time_tz::{timezones, OffsetDateTimeExt};
+
+    /// June 2024 starts on a Saturday, so the 2nd is a Sunday and the 7th a Friday.
+    fn at(day: u8, hour: u8, minute: u8) -> DateTime {
+        Date::from_calendar_date(2024, Month::June, day)
+            .unwrap()
+            .with_time(Time::from_hms(hour, minute, 0).unwrap())
+            .assume_utc()
+    }
+
+    fn schedule(source: &str) -> Schedule {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn ranges_and_lists() {
+        let hours = schedule("0 9-17 * * *");
+        assert!(hours.matches(at(3, 9, 0)));
+        assert!(hours.matches(at(3, 17, 0)));
+        assert!(!hours.matches(at(3, 18, 0)));
+        assert!(!hours.matches(at(3, 9, 1)));
+
+        let days = schedule("30 8 1,15 * *");
+        assert!(days.matches(at(1, 8, 30)));
+        assert!(days.matches(at(15, 8, 30)));
+        assert!(!days.matches(at(14, 8, 30)));
+    }
+
+    #[test]
+    fn steps() {
+        let quarters = schedule("*/15 * * * *");
+        for minute in [0, 15, 30, 45] {
+            assert!(quarters.matches(at(3, 12, minute)));
+        }
+        assert!(!quarters.matches(at(3, 12, 10)));
+
+        let ranged = schedule("0-30/10 * * * *");
+        assert!(ranged.matches(at(3, 12, 20)));
+        assert!(ranged.matches(at(3, 12, 30)));
+        assert!(!ranged.matches(at(3, 12, 40)));
+
+        // A single start runs to the end of the field.
+        let offset = schedule("5/20 * * * *");
+        for minute in [5, 25, 45] {
+            assert!(offset.matches(at(3, 12, minute)));
+        }
+        assert!(!offset.matches(at(3, 12, 0)));
+    }
+
+    #[test]
+    fn sunday_is_zero_or_seven() {
+        for source in ["0 9 * * 0", "0 9 * * 7", "0 9 * * 5-7"] {
+            assert!(schedule(source).matches(at(2, 9, 0)), "{source}");
+            assert!(!schedule(source).matches(at(3, 9, 0)), "{source}");
+        }
+    }
+
+    #[test]
+    fn restricted_day_and_weekday_match_on_either() {
+        let either = schedule("0 9 13 * 5");
+        assert!(either.matches(at(7, 9, 0)));
+        assert!(either.matches(at(13, 9, 0)));
+        assert!(!either.matches(at(12, 9, 0)));
+
+        // With one of them left as `*`, only the other one counts.
+        assert!(!schedule("0 9 13 * *").matches(at(7, 9, 0)));
+        assert!(!schedule("0 9 * * 5").matches(at(13, 9, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        for source in [
+            "0 9 * *",
+            "0 9 * * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "0 17-9 * * *",
+            "0 9 0 * *",
+            "0 9 * 13 *",
+            "0 9 * * 8",
+            "0 nine * * *",
+        ] {
+            assert!(source.parse::<Schedule>().is_err(), "{source}");
+        }
+    }
+
+    #[test]
+    fn displays_the_normalized_source() {
+        assert_eq!(schedule("  0 9\t* * 1 ").to_string(), "0 9 * * 1");
+    }
+
+    #[test]
+    fn matches_in_the_given_timezone() {
+        // Mondays at 9:00 in Berlin are 7:00 UTC in summer.
+        let monday = schedule("0 9 * * 1");
+        let berlin = timezones::db::europe::BERLIN;
+        assert!(monday.matches(at(3, 7, 0).to_timezone(berlin)));
+        assert!(!monday.matches(at(3, 9, 0).to_timezone(berlin)));
+
+        // Sunday evening in New York is already Monday in UTC.
+        let sunday = schedule("0 21 * * 0");
+        let new_york = timezones::db::america::NEW_YORK;
+        assert!(sunday.matches(at(3, 1, 0).to_timezone(new_york)));
+        assert!(!sunday.matches(at(3, 1, 0)));
+        assert!(!sunday.matches(at(2, 21, 0).to_timezone(new_york)));
+    }
+}
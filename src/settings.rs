@@ -0,0 +1,66 @@
+use crate::db::guild_settings::GuildSettings;
+use crate::db::Db;
+use crate::Error;
+use poise::serenity_prelude::GuildId;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use time_tz::{timezones, Tz};
+
+/// Reads and updates per-guild settings. Settings are read on every command, so they are
+/// cached and only written through [`SettingsService::update`].
+#[derive(Clone, Debug)]
+pub struct SettingsService {
+    db: Db,
+    cache: Arc<RwLock<HashMap<u64, GuildSettings>>>,
+}
+
+impl SettingsService {
+    pub fn new(db: Db) -> SettingsService {
+        SettingsService {
+            db,
+            cache: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Settings of `guild`, or the defaults outside of guilds.
+    pub fn get(&self, guild: Option<GuildId>) -> Result<GuildSettings, Error> {
+        let Some(guild) = guild else {
+            return Ok(GuildSettings::default());
+        };
+        if let Some(settings) = self.cache.read().unwrap().get(&guild.get()) {
+            return Ok(settings.clone());
+        }
+        let settings = self.db.guild_settings(guild.get())?;
+        self.cache
+            .write()
+            .unwrap()
+            .insert(guild.get(), settings.clone());
+        Ok(settings)
+    }
+
+    /// Applies `change` to the guild's settings and stores them.
+    pub fn update(
+        &self,
+        guild: GuildId,
+        change: impl FnOnce(&mut GuildSettings),
+    ) -> Result<GuildSettings, Error> {
+        let mut settings = self.get(Some(guild))?;
+        change(&mut settings);
+        self.db.save_guild_settings(guild.get(), &settings)?;
+        self.cache
+            .write()
+            .unwrap()
+            .insert(guild.get(), settings.clone());
+        Ok(settings)
+    }
+}
+
+impl GuildSettings {
+    /// Display timezone, UTC unless set.
+    pub fn timezone(&self) -> &'static Tz {
+        self.timezone
+            .as_deref()
+            .and_then(timezones::get_by_name)
+            .unwrap_or(timezones::db::UTC)
+    }
+}
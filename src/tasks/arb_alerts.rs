@@ -0,0 +1,97 @@
+use crate::analytics::{arbitrage, book};
+use crate::commands::lp::{PoolOrders, PoolPrice};
+use crate::config::Colours;
+use crate::network::Network;
+use crate::prices::PriceFeed;
+use crate::rpc::heads::{self, BlockEvent};
+use crate::Error;
+use jsonrpsee::rpc_params;
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, CreateMessage};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+const QUOTE: &str = "USDC";
+
+#[derive(Clone, Debug)]
+pub struct ArbAlertSettings {
+    pub channel: ChannelId,
+    pub threshold_bps: f64,
+    pub every_blocks: u32,
+    pub colours: Colours,
+}
+
+pub fn spawn(
+    http: Arc<serenity::Http>,
+    network: Network,
+    feed: Arc<dyn PriceFeed>,
+    settings: ArbAlertSettings,
+    mut blocks: broadcast::Receiver<BlockEvent>,
+) {
+    tokio::spawn(async move {
+        // Pools currently past the threshold, so each excursion alerts once.
+        let mut deviating: HashSet<String> = HashSet::new();
+        while let Some(header) = heads::next_head(&mut blocks).await {
+            if header.number.as_u32() % settings.every_blocks != 0 {
+                continue;
+            }
+            for base in network.assets.iter().filter(|a| *a != QUOTE) {
+                if let Err(err) =
+                    check(&http, &network, &*feed, &settings, base, &mut deviating).await
+                {
+                    eprintln!("arb alerts: {base}-{QUOTE}: {err}");
+                }
+            }
+        }
+    });
+}
+
+async fn check(
+    http: &serenity::Http,
+    network: &Network,
+    feed: &dyn PriceFeed,
+    settings: &ArbAlertSettings,
+    base: &str,
+    deviating: &mut HashSet<String>,
+) -> Result<(), Error> {
+    let reference = feed.price(base, QUOTE).await?;
+    let orders: PoolOrders = network
+        .rpc
+        .request("cf_pool_orders", rpc_params![base, QUOTE])
+        .await?;
+    let price: PoolPrice = network
+        .rpc
+        .request("cf_pool_price", rpc_params![base, QUOTE])
+        .await?;
+    let mid = book::mid_price(&orders, base, QUOTE, price.tick);
+    let deviation = arbitrage::deviation_bps(mid, reference);
+    if deviation.abs() < settings.threshold_bps {
+        deviating.remove(base);
+        return Ok(());
+    }
+    if !deviating.insert(base.to_string()) {
+        return Ok(());
+    }
+    let mut embed = network
+        .tag(CreateEmbed::new())
+        .title(format!("Price Deviation {}-{}", base, QUOTE))
+        .colour(settings.colours.warning)
+        .field("Pool mid", format!("{:.4}", mid), true)
+        .field("Reference", format!("{:.4}", reference), true)
+        .field("Deviation", format!("{:+.1} bps", deviation), true);
+    if let Some(arb) = arbitrage::opportunity(&orders, base, QUOTE, price.tick, reference) {
+        embed = embed.field(
+            "Arbitrage until converged",
+            format!(
+                "{:.4} {} ({:.2} {} est. profit)",
+                arb.base_amount, base, arb.profit, QUOTE
+            ),
+            false,
+        );
+    }
+    settings
+        .channel
+        .send_message(http, CreateMessage::new().embed(embed))
+        .await?;
+    Ok(())
+}
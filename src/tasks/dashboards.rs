@@ -0,0 +1,73 @@
+use crate::commands::dashboard;
+use crate::db::Db;
+use crate::network::Network;
+use crate::rpc::heads::{self, BlockEvent};
+use crate::settings::SettingsService;
+use crate::Error;
+use poise::serenity_prelude::{self as serenity, ChannelId, EditMessage, GuildId, MessageId};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Clone, Copy, Debug)]
+pub struct DashboardSettings {
+    /// Edit dashboards every this many blocks.
+    pub every_blocks: u32,
+}
+
+pub fn spawn(
+    http: Arc<serenity::Http>,
+    network: Network,
+    db: Db,
+    guild_settings: SettingsService,
+    settings: DashboardSettings,
+    mut blocks: broadcast::Receiver<BlockEvent>,
+) {
+    tokio::spawn(async move {
+        while let Some(header) = heads::next_head(&mut blocks).await {
+            if header.number.as_u32() % settings.every_blocks != 0 {
+                continue;
+            }
+            if let Err(err) = update(&http, &network, &db, &guild_settings).await {
+                eprintln!("dashboards: {err}");
+            }
+        }
+    });
+}
+
+async fn update(
+    http: &serenity::Http,
+    network: &Network,
+    db: &Db,
+    guild_settings: &SettingsService,
+) -> Result<(), Error> {
+    for dashboard in db.dashboards(&network.name)? {
+        let timezone = guild_settings
+            .get(Some(GuildId::new(dashboard.guild_id)))?
+            .timezone();
+        let embed = match dashboard::render(&dashboard, network, timezone).await {
+            Ok(embed) => embed,
+            Err(err) => {
+                eprintln!("dashboards: {}: {err}", dashboard.id);
+                continue;
+            }
+        };
+        let edited = ChannelId::new(dashboard.channel_id)
+            .edit_message(
+                http,
+                MessageId::new(dashboard.message_id),
+                EditMessage::new().embed(embed),
+            )
+            .await;
+        match edited {
+            Ok(_) => {}
+            // The message or its channel was deleted, or the bot lost access to it.
+            Err(serenity::Error::Http(serenity::HttpError::UnsuccessfulRequest(response)))
+                if matches!(response.status_code.as_u16(), 403 | 404) =>
+            {
+                db.remove_dashboard(dashboard.guild_id, dashboard.message_id)?;
+            }
+            Err(err) => eprintln!("dashboards: {}: {err}", dashboard.id),
+        }
+    }
+    Ok(())
+}
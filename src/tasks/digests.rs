@@ -0,0 +1,137 @@
+use crate::commands::digest::{self, Period, Report};
+use crate::db::digests::Digest;
+use crate::db::Db;
+use crate::network::Network;
+use crate::schedule::Schedule;
+use crate::settings::SettingsService;
+use crate::Error;
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateMessage, GuildId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use time::OffsetDateTime as DateTime;
+use time_tz::OffsetDateTimeExt;
+
+/// Minutes missed since the last check are caught up on, so this only bounds how late a
+/// digest goes out.
+const CHECK_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Missed minutes caught up on after a stall, older ones are dropped rather than posted late.
+const MAX_CATCH_UP_MINUTES: i64 = 15;
+
+pub fn spawn(
+    http: Arc<serenity::Http>,
+    networks: Vec<Network>,
+    db: Db,
+    guild_settings: SettingsService,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut last_checked = None;
+        loop {
+            interval.tick().await;
+            match post_due(&http, &networks, &db, &guild_settings, last_checked).await {
+                Ok(minute) => last_checked = Some(minute),
+                Err(err) => eprintln!("digests: {err}"),
+            }
+        }
+    });
+}
+
+/// Posts the digests scheduled in any minute after `last_checked` up to now, returning the
+/// current minute to pass as `last_checked` next time.
+async fn post_due(
+    http: &serenity::Http,
+    networks: &[Network],
+    db: &Db,
+    guild_settings: &SettingsService,
+    last_checked: Option<i64>,
+) -> Result<i64, Error> {
+    let now = DateTime::now_utc().unix_timestamp();
+    let minute = now - now.rem_euclid(60);
+    let first = last_checked.map_or(minute, |checked| {
+        (checked + 60).max(minute - (MAX_CATCH_UP_MINUTES - 1) * 60)
+    });
+    let minutes = (first..=minute)
+        .step_by(60)
+        .map(DateTime::from_unix_timestamp)
+        .collect::<Result<Vec<_>, _>>()?;
+    // Digests due in the same check share the report.
+    let mut reports: HashMap<(String, Period), Option<Report>> = HashMap::new();
+    for digest in db.digests()? {
+        let schedule = match digest.schedule.parse::<Schedule>() {
+            Ok(schedule) => schedule,
+            Err(err) => {
+                eprintln!("digests: {}: invalid schedule: {err}", digest.id);
+                continue;
+            }
+        };
+        let timezone = guild_settings
+            .get(Some(GuildId::new(digest.guild_id)))?
+            .timezone();
+        // A digest is posted once however many of the minutes it was due in.
+        let due = minutes.iter().any(|at| {
+            digest
+                .last_sent
+                .is_none_or(|sent| sent < at.unix_timestamp())
+                && schedule.matches(at.to_timezone(timezone))
+        });
+        if !due {
+            continue;
+        }
+        let (Some(period), Some(network)) = (
+            Period::from_key(&digest.period),
+            networks
+                .iter()
+                .find(|network| network.name == digest.network),
+        ) else {
+            eprintln!("digests: {}: unknown period or network", digest.id);
+            continue;
+        };
+        let key = (network.name.clone(), period);
+        if !reports.contains_key(&key) {
+            let report = match digest::gather(network, db, period).await {
+                Ok(report) => Some(report),
+                Err(err) => {
+                    eprintln!("digests: {} {}: {err}", network.name, period.key());
+                    None
+                }
+            };
+            reports.insert(key.clone(), report);
+        }
+        let Some(report) = &reports[&key] else {
+            continue;
+        };
+        post(http, db, &digest, report, network, timezone).await?;
+    }
+    Ok(minute)
+}
+
+async fn post(
+    http: &serenity::Http,
+    db: &Db,
+    digest: &Digest,
+    report: &Report,
+    network: &Network,
+    timezone: &time_tz::Tz,
+) -> Result<(), Error> {
+    let embeds = digest::render(report, network, digest.authorities.as_ref(), timezone)?;
+    let sent = ChannelId::new(digest.channel_id)
+        .send_message(http, CreateMessage::new().embeds(embeds))
+        .await;
+    match sent {
+        Ok(_) => db.mark_digest_sent(
+            digest.id,
+            DateTime::now_utc().unix_timestamp(),
+            report.authorities.as_ref(),
+        )?,
+        // The channel was deleted, or the bot lost access to it.
+        Err(serenity::Error::Http(serenity::HttpError::UnsuccessfulRequest(response)))
+            if matches!(response.status_code.as_u16(), 403 | 404) =>
+        {
+            db.remove_digest(digest.guild_id, digest.id)?;
+        }
+        Err(err) => eprintln!("digests: {}: {err}", digest.id),
+    }
+    Ok(())
+}
@@ -0,0 +1,7 @@
+pub mod arb_alerts;
+pub mod dashboards;
+pub mod digests;
+pub mod presence;
+pub mod recorder;
+pub mod swap_feed;
+pub mod validator_alerts;
@@ -0,0 +1,96 @@
+use crate::commands::cf::{AuctionState, BlockHeader};
+use crate::commands::lp::PoolPrice;
+use crate::network::Network;
+use crate::rpc::heads::{self, BlockEvent};
+use crate::util::util::tick_to_price;
+use crate::Error;
+use jsonrpsee::rpc_params;
+use poise::serenity_prelude::{self as serenity, ActivityData};
+use tokio::sync::broadcast;
+
+#[derive(Clone, Debug)]
+pub struct PresenceSettings {
+    /// Show the next item every this many blocks.
+    pub every_blocks: u32,
+    /// Base and quote asset of the pool whose price is shown, if any.
+    pub pool: Option<(String, String)>,
+}
+
+/// What the presence shows, in the order it rotates through.
+#[derive(Clone, Copy, Debug)]
+enum Item {
+    Block,
+    Epoch,
+    Rotation,
+    Pool,
+}
+
+pub fn spawn(
+    ctx: serenity::Context,
+    network: Network,
+    settings: PresenceSettings,
+    mut blocks: broadcast::Receiver<BlockEvent>,
+) {
+    let mut items = vec![Item::Block, Item::Epoch, Item::Rotation];
+    if settings.pool.is_some() {
+        items.push(Item::Pool);
+    }
+    tokio::spawn(async move {
+        let mut next = 0;
+        while let Some(header) = heads::next_head(&mut blocks).await {
+            if header.number.as_u32() % settings.every_blocks != 0 {
+                continue;
+            }
+            let item = items[next % items.len()];
+            next += 1;
+            match describe(item, &network, &settings, &header).await {
+                Ok(text) => ctx.set_activity(Some(ActivityData::custom(text))),
+                Err(err) => eprintln!("presence: {err}"),
+            }
+        }
+    });
+}
+
+async fn describe(
+    item: Item,
+    network: &Network,
+    settings: &PresenceSettings,
+    header: &BlockHeader,
+) -> Result<String, Error> {
+    let rpc = &network.rpc;
+    let text = match item {
+        Item::Block => format!("Block #{}", header.number),
+        Item::Epoch => {
+            let epoch: u32 = rpc.request("cf_current_epoch", rpc_params![]).await?;
+            format!("Epoch {}", epoch)
+        }
+        Item::Rotation => {
+            let auction: AuctionState = rpc.request("cf_auction_state", rpc_params![]).await?;
+            let epoch_started_at: u32 = rpc
+                .request("cf_current_epoch_started_at", rpc_params![])
+                .await?;
+            let blocks_to_rotation = auction
+                .blocks_per_epoch
+                .saturating_sub(header.number.as_u32().saturating_sub(epoch_started_at));
+            let minutes = blocks_to_rotation as u64 * 6 / 60;
+            match minutes {
+                0 => "Rotation imminent".to_string(),
+                m if m < 60 => format!("Rotation in {}m", m),
+                m if m < 24 * 60 => format!("Rotation in {}h {}m", m / 60, m % 60),
+                m => format!("Rotation in {}d {}h", m / (24 * 60), m % (24 * 60) / 60),
+            }
+        }
+        Item::Pool => {
+            let (base, quote) = settings.pool.as_ref().ok_or("no pool configured")?;
+            let price: PoolPrice = rpc
+                .request("cf_pool_price", rpc_params![base, quote])
+                .await?;
+            let price = tick_to_price(price.tick, base, quote);
+            match price {
+                p if p >= 100.0 => format!("{} {:.2} {}", base, p, quote),
+                p => format!("{} {:.4} {}", base, p, quote),
+            }
+        }
+    };
+    Ok(text)
+}
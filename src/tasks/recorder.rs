@@ -0,0 +1,217 @@
+use crate::analytics::{book, concentration, volume};
+use crate::commands::cf::AuctionState;
+use crate::commands::lp::{PoolOrders, PoolPrice};
+use crate::db::snapshots::{AuctionSnapshot, PoolSnapshot, HOUR, MINUTE, RAW};
+use crate::db::Db;
+use crate::network::Network;
+use crate::rpc::heads::{self, BlockEvent};
+use crate::rpc::pool::RpcPool;
+use crate::util::util::asset_in_amount;
+use crate::Error;
+use jsonrpsee::rpc_params;
+use rust_decimal::prelude::*;
+use std::collections::HashMap;
+use std::time::Duration;
+use time::OffsetDateTime as DateTime;
+use tokio::sync::broadcast;
+use web3::types::H256;
+
+pub const QUOTE: &str = "USDC";
+const COMPACTION_INTERVAL: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Debug)]
+pub struct RecorderSettings {
+    /// Record every this many blocks.
+    pub every_blocks: u32,
+    pub raw_retention: Duration,
+    pub minute_retention: Duration,
+    pub hour_retention: Duration,
+}
+
+pub fn spawn(
+    network: Network,
+    db: Db,
+    settings: RecorderSettings,
+    mut blocks: broadcast::Receiver<BlockEvent>,
+) {
+    let compaction_db = db.clone();
+    let compaction_settings = settings.clone();
+    tokio::spawn(async move {
+        let mut recorder = Recorder::new(network.assets.clone());
+        while let Some(header) = heads::next_head(&mut blocks).await {
+            let head = header.number.as_u32();
+            if head % settings.every_blocks != 0 {
+                continue;
+            }
+            let timestamp = DateTime::now_utc().unix_timestamp();
+            if let Err(err) = recorder
+                .record(&network.rpc, &db, head, None, timestamp)
+                .await
+            {
+                eprintln!("recorder: {err}");
+            }
+        }
+    });
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(COMPACTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(err) = compact(&compaction_db, &compaction_settings) {
+                eprintln!("recorder compaction: {err}");
+            }
+        }
+    });
+}
+
+/// A pool as last read by the recorder.
+struct PoolState {
+    block: u32,
+    tick: i32,
+    orders: PoolOrders,
+}
+
+/// Records pool and auction snapshots. The last order book of each pool is kept to infer
+/// fills from the next one.
+pub struct Recorder {
+    assets: Vec<String>,
+    previous: HashMap<String, PoolState>,
+}
+
+impl Recorder {
+    /// Records the pools of `assets` against the quote asset.
+    pub fn new(assets: Vec<String>) -> Recorder {
+        Recorder {
+            assets,
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Reads every pool at `block` without recording it, so fills are inferred from the first
+    /// block recorded after it, e.g. when resuming a backfill.
+    pub async fn seed(&mut self, rpc: &RpcPool, block: u32, hash: H256) {
+        for base in self.assets.iter().filter(|a| *a != QUOTE) {
+            match read_pool(rpc, base, Some(hash)).await {
+                Ok((orders, tick)) => {
+                    self.previous.insert(
+                        base.clone(),
+                        PoolState {
+                            block,
+                            tick,
+                            orders,
+                        },
+                    );
+                }
+                Err(err) => eprintln!("recorder: {base}-{QUOTE} at block {block}: {err}"),
+            }
+        }
+    }
+
+    /// Records every pool and the auction state at `block`, whose hash is `None` for the
+    /// latest block. A pool that fails (e.g. because it didn't exist yet) doesn't stop the
+    /// others.
+    pub async fn record(
+        &mut self,
+        rpc: &RpcPool,
+        db: &Db,
+        block: u32,
+        hash: Option<H256>,
+        timestamp: i64,
+    ) -> Result<(), Error> {
+        // Cloned since recording a pool updates the previous order books.
+        for base in self.assets.clone().iter().filter(|a| *a != QUOTE) {
+            if let Err(err) = self
+                .record_pool(rpc, db, base, block, hash, timestamp)
+                .await
+            {
+                eprintln!("recorder: {base}-{QUOTE} at block {block}: {err}");
+            }
+        }
+        let auction: AuctionState = rpc.request("cf_auction_state", rpc_params![hash]).await?;
+        db.insert_auction_snapshot(&AuctionSnapshot {
+            block,
+            timestamp,
+            epoch_started_at: auction.current_epoch_started_at,
+            blocks_per_epoch: auction.blocks_per_epoch,
+            min_active_bid: asset_in_amount(&auction.min_active_bid, "FLIP")
+                .to_f64()
+                .unwrap_or_default(),
+        })?;
+        Ok(())
+    }
+
+    async fn record_pool(
+        &mut self,
+        rpc: &RpcPool,
+        db: &Db,
+        base: &str,
+        block: u32,
+        hash: Option<H256>,
+        timestamp: i64,
+    ) -> Result<(), Error> {
+        let (orders, tick) = read_pool(rpc, base, hash).await?;
+        let summary = book::summarize(&orders, base, QUOTE, tick);
+        db.insert_pool_snapshot(&PoolSnapshot::new(base, QUOTE, timestamp, block, &summary))?;
+        db.set_best_quote_holders(
+            base,
+            QUOTE,
+            timestamp,
+            &concentration::best_quote_holders(&orders.limit_orders.bids, book::best_bid(&orders)),
+            &concentration::best_quote_holders(&orders.limit_orders.asks, book::best_ask(&orders)),
+        )?;
+        match self.previous.get(base) {
+            Some(previous) if previous.block < block => {
+                let fills = volume::estimate(
+                    (&previous.orders, previous.tick),
+                    (&orders, tick),
+                    base,
+                    QUOTE,
+                    summary.mid,
+                );
+                db.insert_pool_fills(base, QUOTE, timestamp, &fills)?;
+            }
+            _ => {}
+        }
+        self.previous.insert(
+            base.to_string(),
+            PoolState {
+                block,
+                tick,
+                orders,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// The order book of a pool and its current tick.
+async fn read_pool(
+    rpc: &RpcPool,
+    base: &str,
+    hash: Option<H256>,
+) -> Result<(PoolOrders, i32), Error> {
+    let orders: PoolOrders = rpc
+        .request("cf_pool_orders", rpc_params![base, QUOTE, hash])
+        .await?;
+    let price: PoolPrice = rpc
+        .request("cf_pool_price", rpc_params![base, QUOTE, hash])
+        .await?;
+    Ok((orders, price.tick))
+}
+
+fn compact(db: &Db, settings: &RecorderSettings) -> Result<(), Error> {
+    let now = DateTime::now_utc().unix_timestamp();
+    let cutoff = |retention: Duration, resolution: i64| {
+        let before = now - retention.as_secs() as i64;
+        before - before.rem_euclid(resolution)
+    };
+    db.compact_pool_snapshots(RAW, MINUTE, cutoff(settings.raw_retention, MINUTE))?;
+    db.compact_pool_snapshots(MINUTE, HOUR, cutoff(settings.minute_retention, HOUR))?;
+    db.delete_pool_snapshots(HOUR, now - settings.hour_retention.as_secs() as i64)?;
+    db.compact_pool_fills(RAW, MINUTE, cutoff(settings.raw_retention, MINUTE))?;
+    db.compact_pool_fills(MINUTE, HOUR, cutoff(settings.minute_retention, HOUR))?;
+    db.delete_pool_fills(HOUR, now - settings.hour_retention.as_secs() as i64)?;
+    db.compact_best_quotes(RAW, MINUTE, cutoff(settings.raw_retention, MINUTE))?;
+    db.compact_best_quotes(MINUTE, HOUR, cutoff(settings.minute_retention, HOUR))?;
+    db.delete_best_quotes(HOUR, now - settings.hour_retention.as_secs() as i64)?;
+    Ok(())
+}
@@ -0,0 +1,290 @@
+use crate::commands::lp::PoolPrice;
+use crate::config::Colours;
+use crate::network::Network;
+use crate::rpc::heads::{self, BlockEvent};
+use crate::util::util::{asset_in_amount, shorten_address, tick_to_price};
+use crate::Error;
+use jsonrpsee::rpc_params;
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, CreateMessage};
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use web3::types::{H256, U256};
+
+const QUOTE: &str = "USDC";
+
+#[derive(Clone, Debug)]
+pub struct SwapFeedSettings {
+    pub channel: ChannelId,
+    pub min_usd: Decimal,
+    /// Poll scheduled swaps every this many blocks.
+    pub every_blocks: u32,
+    pub colours: Colours,
+}
+
+/// Scheduled swaps are read at every block since the last poll, up to this many back, so
+/// swaps executed between polls are still seen without flooding the channel after an outage.
+const MAX_CATCH_UP: u32 = 50;
+
+/// Deposits posted while prewitnessed are remembered this many blocks, so they aren't posted
+/// again once they are scheduled.
+const PREWITNESS_MEMORY: u32 = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ScheduledSwap {
+    pub swap_id: U256,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub side: Side,
+    pub amount: U256,
+    pub source_asset: Option<String>,
+    pub source_amount: Option<U256>,
+    pub execute_at: u32,
+    #[serde(default)]
+    pub broker: Option<String>,
+}
+
+/// Deposits witnessed ahead of confirmation, which only carry their amounts.
+#[derive(Deserialize, Clone, Debug)]
+pub struct PrewitnessedSwaps {
+    pub amounts: Vec<U256>,
+}
+
+/// The asset going into a leg of a swap and the one coming out of it.
+fn legs<'a>(side: Side, base: &'a str, quote: &'a str) -> (&'a str, &'a str) {
+    match side {
+        Side::Sell => (base, quote),
+        Side::Buy => (quote, base),
+    }
+}
+
+/// A deposit posted while prewitnessed, matched later by the swap scheduled for it.
+struct Deposit {
+    asset: String,
+    amount: U256,
+    seen_at: u32,
+}
+
+#[derive(Default)]
+struct Seen {
+    /// Swap id -> block it executes at, so posted swaps can be forgotten once executed.
+    scheduled: HashMap<U256, u32>,
+    /// Amounts last reported as prewitnessed per pool and side. They carry no id, so the same
+    /// amount listed twice is two deposits.
+    prewitnessed: HashMap<(String, Side), Vec<U256>>,
+    deposits: Vec<Deposit>,
+    last_polled: Option<u32>,
+}
+
+pub fn spawn(
+    http: Arc<serenity::Http>,
+    network: Network,
+    settings: SwapFeedSettings,
+    mut blocks: broadcast::Receiver<BlockEvent>,
+) {
+    tokio::spawn(async move {
+        let mut seen = Seen::default();
+        while let Some(header) = heads::next_head(&mut blocks).await {
+            let head = header.number.as_u32();
+            if head % settings.every_blocks != 0 {
+                continue;
+            }
+            if let Err(err) = poll(&http, &network, &settings, head, &mut seen).await {
+                eprintln!("swap feed: {err}");
+            }
+        }
+    });
+}
+
+async fn poll(
+    http: &serenity::Http,
+    network: &Network,
+    settings: &SwapFeedSettings,
+    head: u32,
+    seen: &mut Seen,
+) -> Result<(), Error> {
+    seen.scheduled.retain(|_, execute_at| *execute_at >= head);
+    seen.deposits
+        .retain(|deposit| deposit.seen_at + PREWITNESS_MEMORY >= head);
+    let from = seen
+        .last_polled
+        .map_or(head, |last| last + 1)
+        .max(head.saturating_sub(MAX_CATCH_UP - 1));
+    // Resolved once, every pool is read at the same blocks.
+    let mut hashes = Vec::new();
+    for block in from..=head {
+        let hash: Option<H256> = network
+            .rpc
+            .request("chain_getBlockHash", rpc_params![block])
+            .await?;
+        hashes.extend(hash);
+    }
+
+    for base in network.assets.iter().filter(|a| *a != QUOTE) {
+        let price: PoolPrice = network
+            .rpc
+            .request("cf_pool_price", rpc_params![base, QUOTE])
+            .await?;
+        let price = Decimal::from_f32(tick_to_price(price.tick, base, QUOTE)).unwrap_or_default();
+
+        for side in [Side::Buy, Side::Sell] {
+            let prewitnessed: PrewitnessedSwaps = network
+                .rpc
+                .request("cf_prewitness_swaps", rpc_params![base, QUOTE, side])
+                .await?;
+            let previous = seen
+                .prewitnessed
+                .insert((base.to_string(), side), prewitnessed.amounts.clone())
+                .unwrap_or_default();
+            let (from_asset, to_asset) = legs(side, base, QUOTE);
+            for amount in new_amounts(&previous, &prewitnessed.amounts) {
+                let value = asset_in_amount(&amount, from_asset);
+                let (usd_value, estimated_out) = estimate(side, value, price);
+                if usd_value < settings.min_usd {
+                    continue;
+                }
+                let embed = swap_embed(
+                    "Incoming Swap",
+                    side,
+                    (from_asset, value),
+                    (to_asset, estimated_out),
+                    usd_value,
+                    None,
+                    &settings.colours,
+                )
+                .field("Status", "Prewitnessed", true);
+                post(http, network, settings, embed).await?;
+                seen.deposits.push(Deposit {
+                    asset: from_asset.to_string(),
+                    amount,
+                    seen_at: head,
+                });
+            }
+        }
+
+        for hash in &hashes {
+            let swaps: Vec<ScheduledSwap> = network
+                .rpc
+                .request("cf_scheduled_swaps", rpc_params![base, QUOTE, hash])
+                .await?;
+            for swap in swaps {
+                if seen.scheduled.contains_key(&swap.swap_id) {
+                    continue;
+                }
+                seen.scheduled.insert(swap.swap_id, swap.execute_at);
+                let (from_asset, to_asset) = legs(swap.side, &swap.base_asset, &swap.quote_asset);
+                let (source_asset, source_amount) = match (&swap.source_asset, swap.source_amount) {
+                    (Some(asset), Some(amount)) => (asset.as_str(), amount),
+                    _ => (from_asset, swap.amount),
+                };
+                // Already posted while its deposit was prewitnessed.
+                let posted = seen.deposits.iter().position(|deposit| {
+                    deposit.asset == source_asset && deposit.amount == source_amount
+                });
+                if let Some(posted) = posted {
+                    seen.deposits.swap_remove(posted);
+                    continue;
+                }
+                let amount = asset_in_amount(&swap.amount, from_asset);
+                let (usd_value, estimated_out) = estimate(swap.side, amount, price);
+                if usd_value < settings.min_usd {
+                    continue;
+                }
+                let embed = swap_embed(
+                    "Large Swap",
+                    swap.side,
+                    (source_asset, asset_in_amount(&source_amount, source_asset)),
+                    (to_asset, estimated_out),
+                    usd_value,
+                    swap.broker.as_deref(),
+                    &settings.colours,
+                )
+                .field("Swap ID", format!("{}", swap.swap_id), true)
+                .field("Executes at", format!("{}", swap.execute_at), true);
+                post(http, network, settings, embed).await?;
+            }
+        }
+    }
+    seen.last_polled = Some(head);
+    Ok(())
+}
+
+/// Amounts in `current` that weren't in `previous`, counting repeated amounts separately.
+fn new_amounts(previous: &[U256], current: &[U256]) -> Vec<U256> {
+    let mut previous = previous.to_vec();
+    current
+        .iter()
+        .filter(|amount| match previous.iter().position(|p| p == *amount) {
+            Some(index) => {
+                previous.swap_remove(index);
+                false
+            }
+            None => true,
+        })
+        .copied()
+        .collect()
+}
+
+/// USD value of a leg and the estimated amount out, from the pool price.
+fn estimate(side: Side, amount: Decimal, price: Decimal) -> (Decimal, Decimal) {
+    match side {
+        Side::Sell => (amount * price, amount * price),
+        Side::Buy if price.is_zero() => (amount, Decimal::ZERO),
+        Side::Buy => (amount, amount / price),
+    }
+}
+
+async fn post(
+    http: &serenity::Http,
+    network: &Network,
+    settings: &SwapFeedSettings,
+    embed: CreateEmbed,
+) -> Result<(), Error> {
+    settings
+        .channel
+        .send_message(http, CreateMessage::new().embed(network.tag(embed)))
+        .await?;
+    Ok(())
+}
+
+fn swap_embed(
+    title: &str,
+    side: Side,
+    (source_asset, source_amount): (&str, Decimal),
+    (destination_asset, estimated_out): (&str, Decimal),
+    usd_value: Decimal,
+    broker: Option<&str>,
+    colours: &Colours,
+) -> CreateEmbed {
+    CreateEmbed::new()
+        .title(format!("{} {}-{}", title, source_asset, destination_asset))
+        .colour(match side {
+            Side::Buy => colours.positive,
+            Side::Sell => colours.negative,
+        })
+        .field(
+            "Source",
+            format!("{} {}", source_amount.round_dp(4), source_asset),
+            true,
+        )
+        .field(
+            "Destination (est.)",
+            format!("{} {}", estimated_out.round_dp(4), destination_asset),
+            true,
+        )
+        .field("Value (USD)", format!("{}", usd_value.round_dp(2)), true)
+        .field(
+            "Broker",
+            broker.map_or("Unknown".to_string(), shorten_address),
+            true,
+        )
+}
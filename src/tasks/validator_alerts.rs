@@ -0,0 +1,235 @@
+use crate::commands::cf::{AccountInfo, AuctionState};
+use crate::config::Colours;
+use crate::db::watchlist::ValidatorWatch;
+use crate::db::Db;
+use crate::network::Network;
+use crate::rpc::heads::{self, BlockEvent};
+use crate::rpc::pool::RpcPool;
+use crate::Error;
+use jsonrpsee::rpc_params;
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, CreateMessage};
+use serenity::{Mentionable, UserId};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Clone, Debug)]
+pub struct AlertSettings {
+    /// Check watches every this many blocks.
+    pub every_blocks: u32,
+    /// Default for watches that don't set their own lag threshold.
+    pub max_heartbeat_lag: u32,
+    /// Default for watches that don't set their own reputation threshold.
+    pub min_reputation: i32,
+    /// How many blocks before the rotation a validator is expected to be bidding.
+    pub auction_window: u32,
+    pub colours: Colours,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Problem {
+    Offline,
+    HeartbeatStale,
+    LowReputation,
+    NegativeReputation,
+    Unqualified,
+    NotBidding,
+}
+
+impl Problem {
+    fn describe(&self) -> &'static str {
+        match self {
+            Problem::Offline => "Validator is offline",
+            Problem::HeartbeatStale => "Last heartbeat is too far behind the head",
+            Problem::LowReputation => "Reputation is below the threshold",
+            Problem::NegativeReputation => "Reputation is negative",
+            Problem::Unqualified => "Validator is not qualified",
+            Problem::NotBidding => "Validator is not bidding ahead of the auction",
+        }
+    }
+}
+
+struct ValidatorState {
+    last_heartbeat: u32,
+    reputation_points: i32,
+    is_qualified: bool,
+    is_online: bool,
+    is_bidding: bool,
+}
+
+pub fn spawn(
+    http: Arc<serenity::Http>,
+    network: Network,
+    db: Db,
+    settings: AlertSettings,
+    mut blocks: broadcast::Receiver<BlockEvent>,
+) {
+    tokio::spawn(async move {
+        let mut known: HashMap<i64, BTreeSet<Problem>> = HashMap::new();
+        while let Some(header) = heads::next_head(&mut blocks).await {
+            let head = header.number.as_u32();
+            if head % settings.every_blocks != 0 {
+                continue;
+            }
+            if let Err(err) = check(&http, &network, &db, &settings, head, &mut known).await {
+                eprintln!("validator alerts: {err}");
+            }
+        }
+    });
+}
+
+async fn check(
+    http: &serenity::Http,
+    network: &Network,
+    db: &Db,
+    settings: &AlertSettings,
+    head: u32,
+    known: &mut HashMap<i64, BTreeSet<Problem>>,
+) -> Result<(), Error> {
+    let watches = db.validator_watches(&network.name)?;
+    known.retain(|id, _| watches.iter().any(|w| w.id == *id));
+    if watches.is_empty() {
+        return Ok(());
+    }
+    let auction: AuctionState = network
+        .rpc
+        .request("cf_auction_state", rpc_params![])
+        .await?;
+    let blocks_to_rotation = auction
+        .blocks_per_epoch
+        .saturating_sub(head.saturating_sub(auction.current_epoch_started_at));
+
+    let mut states: HashMap<&str, Option<ValidatorState>> = HashMap::new();
+    for watch in &watches {
+        if !states.contains_key(watch.account.as_str()) {
+            // A failed lookup only skips this account's watches until the next check.
+            let state = validator_state(&network.rpc, &watch.account)
+                .await
+                .unwrap_or_else(|err| {
+                    eprintln!("validator alerts: {}: {err}", watch.account);
+                    None
+                });
+            states.insert(&watch.account, state);
+        }
+        let Some(state) = &states[watch.account.as_str()] else {
+            continue;
+        };
+        let current = problems(state, watch, settings, head, blocks_to_rotation);
+        let previous = known.get(&watch.id).cloned().unwrap_or_default();
+        let raised: Vec<_> = current.difference(&previous).copied().collect();
+        let resolved: Vec<_> = previous.difference(&current).copied().collect();
+        // Problems are only marked as known once notified, so a failed send is retried.
+        let mut notified = previous.clone();
+        for (problems, raise) in [(&raised, true), (&resolved, false)] {
+            if problems.is_empty() {
+                continue;
+            }
+            match notify(http, network, settings, watch, state, head, problems, raise).await {
+                Ok(()) => match raise {
+                    true => notified.extend(problems),
+                    false => notified.retain(|problem| !problems.contains(problem)),
+                },
+                Err(err) => eprintln!("validator alerts: watch {}: {err}", watch.id),
+            }
+        }
+        known.insert(watch.id, notified);
+    }
+    Ok(())
+}
+
+async fn validator_state(rpc: &RpcPool, account: &str) -> Result<Option<ValidatorState>, Error> {
+    let info: AccountInfo = rpc.request("cf_account_info", rpc_params![account]).await?;
+    Ok(match info {
+        AccountInfo::Validator {
+            last_heartbeat,
+            reputation_points,
+            is_qualified,
+            is_online,
+            is_bidding,
+            ..
+        } => Some(ValidatorState {
+            last_heartbeat,
+            reputation_points,
+            is_qualified,
+            is_online,
+            is_bidding,
+        }),
+        _ => None,
+    })
+}
+
+fn problems(
+    state: &ValidatorState,
+    watch: &ValidatorWatch,
+    settings: &AlertSettings,
+    head: u32,
+    blocks_to_rotation: u32,
+) -> BTreeSet<Problem> {
+    let max_lag = watch
+        .max_heartbeat_lag
+        .unwrap_or(settings.max_heartbeat_lag);
+    let min_reputation = watch.min_reputation.unwrap_or(settings.min_reputation);
+    let mut problems = BTreeSet::new();
+    if !state.is_online {
+        problems.insert(Problem::Offline);
+    }
+    if head.saturating_sub(state.last_heartbeat) > max_lag {
+        problems.insert(Problem::HeartbeatStale);
+    }
+    if state.reputation_points < 0 {
+        problems.insert(Problem::NegativeReputation);
+    } else if state.reputation_points < min_reputation {
+        problems.insert(Problem::LowReputation);
+    }
+    if !state.is_qualified {
+        problems.insert(Problem::Unqualified);
+    }
+    if !state.is_bidding && blocks_to_rotation <= settings.auction_window {
+        problems.insert(Problem::NotBidding);
+    }
+    problems
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn notify(
+    http: &serenity::Http,
+    network: &Network,
+    settings: &AlertSettings,
+    watch: &ValidatorWatch,
+    state: &ValidatorState,
+    head: u32,
+    problems: &[Problem],
+    raised: bool,
+) -> Result<(), Error> {
+    let (title, colour) = match raised {
+        true => ("Validator alert", settings.colours.negative),
+        false => ("Validator recovered", settings.colours.positive),
+    };
+    let description = problems
+        .iter()
+        .map(|p| format!("- {}", p.describe()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    ChannelId::new(watch.channel_id)
+        .send_message(
+            http,
+            CreateMessage::new()
+                .content(UserId::new(watch.user_id).mention().to_string())
+                .embed(
+                    network
+                        .tag(CreateEmbed::new())
+                        .title(title)
+                        .colour(colour)
+                        .description(description)
+                        .field("Account", watch.account.clone(), false)
+                        .field("Reputation", format!("{}", state.reputation_points), true)
+                        .field(
+                            "Heartbeat lag",
+                            format!("{} blocks", head.saturating_sub(state.last_heartbeat)),
+                            true,
+                        ),
+                ),
+        )
+        .await?;
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use rust_decimal::prelude::*;
 use web3::types::U256;
 
-pub fn shorten_address(addr: &String) -> String {
+pub fn shorten_address(addr: &str) -> String {
     format!(
         "{}{}{}",
         &addr[..addr.char_indices().nth(4).unwrap().0],
@@ -21,7 +21,7 @@ pub fn asset_in_amount(amount: &U256, asset: &str) -> Decimal {
         _ => panic!("Unknown asset: {}", asset),
     }
 }
-fn get_decimals(asset: &str) -> i32 {
+pub fn get_decimals(asset: &str) -> i32 {
     match asset {
         "DOT" => 10,
         "ETH" => 18,
@@ -42,9 +42,29 @@ pub fn tick_to_price(tick: i32, base_asset: &str, quote_asset: &str) -> f32 {
             .unwrap())
 }
 
+// Inverse of `tick_to_price`, rounded to the nearest tick.
+pub fn price_to_tick(price: f64, base_asset: &str, quote_asset: &str) -> i32 {
+    let scale = 10_f64.powi(get_decimals(base_asset) - get_decimals(quote_asset));
+    ((price / scale).ln() / 1.0001_f64.ln()).round() as i32
+}
+
 pub fn bool_to_emoji(b: bool) -> String {
     match b {
         true => "✅".to_string(),
         _ => "❌".to_string(),
     }
 }
+
+/// Parses a duration like `90m`, `24h` or `7d` into seconds.
+pub fn parse_duration(s: &str) -> Option<i64> {
+    let s = s.trim();
+    let (i, unit) = s.char_indices().next_back()?;
+    let unit = match unit {
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+    let amount: i64 = s[..i].parse().ok().filter(|amount| *amount > 0)?;
+    amount.checked_mul(unit)
+}